@@ -0,0 +1,56 @@
+use std::time::Instant;
+
+use cells::{cell::Cell, update::UpdateMode, world::World};
+
+const SIZE_MAP: (usize, usize) = (400, 400);
+const TICKS: usize = 200;
+const RUNS: usize = 5;
+
+fn populated_world(update: UpdateMode) -> World {
+    let mut world = World::with_size_and_seed(SIZE_MAP.0, SIZE_MAP.1, 42);
+    world.nutrient_medium = 8.0;
+    world.config.update = update;
+
+    for y in 0..SIZE_MAP.1 {
+        for x in 0..SIZE_MAP.0 {
+            if (x + y) % 3 != 0 {
+                let cell = Cell::new((x, y), &world.config);
+                world.spawn(cell);
+            }
+        }
+    }
+
+    world
+}
+
+fn main() {
+    for update in [UpdateMode::Sequential, UpdateMode::Synchronous, UpdateMode::Parallel] {
+        // Best of a few runs, the same seed gives the same work every time.
+        let mut best = f64::MAX;
+        let (mut population, mut updates, mut world) = (0, 0, populated_world(update));
+        for _ in 0..RUNS {
+            world = populated_world(update);
+            population = world.cells.len();
+
+            updates = 0;
+            let start = Instant::now();
+            for _ in 0..TICKS {
+                updates += world.cells.len();
+                world.step();
+            }
+            best = best.min(start.elapsed().as_secs_f64());
+        }
+        let elapsed = best;
+
+        println!(
+            "{:?}: {} -> {} cells, {} ticks in {:.3} s ({:.1} ticks/s, {:.2} M cell updates/s)",
+            update,
+            population,
+            world.cells.len(),
+            TICKS,
+            elapsed,
+            TICKS as f64 / elapsed,
+            updates as f64 / elapsed / 1e6,
+        );
+    }
+}
@@ -0,0 +1,191 @@
+use std::{
+    env,
+    fs::File,
+    io::{self, BufWriter, Write},
+    process,
+    str::FromStr,
+};
+
+use cells::{
+    config::SimConfig,
+    info::Info,
+    world::{World, DEFAULT_SIZE_MAP},
+};
+
+const USAGE: &str = "\
+Usage: cells-cli [OPTIONS]
+
+Options:
+    --ticks <N>        Number of ticks to simulate (default: 1000)
+    --seed <SEED>      Seed of the world RNG (default: random)
+    --width <N>        Width of the world (default: 50)
+    --height <N>       Height of the world (default: 50)
+    --every <N>        Print statistics every N ticks (default: 100)
+    --nutrient <F>     Nutrient medium of the world (default: 4.0)
+    --config <PATH>    Simulation constants in RON format (default: built-in)
+    --output <PATH>    Write statistics to a file instead of stdout
+    --load <PATH>      Continue a saved world instead of creating a new one,
+                       cannot be combined with --seed, --width or --height
+    --save <PATH>      Save the world when the run is finished
+    --help             Print this message";
+
+struct Args {
+    ticks: usize,
+    seed: Option<u64>,
+    width: Option<usize>,
+    height: Option<usize>,
+    every: usize,
+    nutrient: Option<f32>,
+    config: Option<String>,
+    output: Option<String>,
+    load: Option<String>,
+    save: Option<String>,
+}
+
+impl Args {
+    fn parse() -> Result<Self, String> {
+        let mut args = Self {
+            ticks: 1000,
+            seed: None,
+            width: None,
+            height: None,
+            every: 100,
+            nutrient: None,
+            config: None,
+            output: None,
+            load: None,
+            save: None,
+        };
+
+        let mut iter = env::args().skip(1);
+        while let Some(arg) = iter.next() {
+            if arg == "--help" {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+
+            let value = iter.next().ok_or(format!("missing value for {}", arg))?;
+            match arg.as_str() {
+                "--ticks" => args.ticks = parse(&arg, &value)?,
+                "--seed" => args.seed = Some(parse(&arg, &value)?),
+                "--width" => args.width = Some(parse(&arg, &value)?),
+                "--height" => args.height = Some(parse(&arg, &value)?),
+                "--every" => args.every = parse::<usize>(&arg, &value)?.max(1),
+                "--nutrient" => args.nutrient = Some(parse(&arg, &value)?),
+                "--config" => args.config = Some(value),
+                "--output" => args.output = Some(value),
+                "--load" => args.load = Some(value),
+                "--save" => args.save = Some(value),
+                _ => return Err(format!("unknown option {}", arg)),
+            }
+        }
+
+        // A saved world already has its seed and size.
+        let new_world = args.seed.is_some() || args.width.is_some() || args.height.is_some();
+        if args.load.is_some() && new_world {
+            return Err("--seed, --width and --height cannot be used with --load".to_string());
+        }
+
+        Ok(args)
+    }
+
+    fn size(&self) -> (usize, usize) {
+        (
+            self.width.unwrap_or(DEFAULT_SIZE_MAP.0),
+            self.height.unwrap_or(DEFAULT_SIZE_MAP.1),
+        )
+    }
+}
+
+fn parse<T: FromStr>(arg: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("invalid value for {}: {}", arg, value))
+}
+
+fn main() {
+    let args = Args::parse().unwrap_or_else(|err| {
+        eprintln!("error: {}\n\n{}", err, USAGE);
+        process::exit(1);
+    });
+
+    let size = args.size();
+    if size.0 == 0 || size.1 == 0 {
+        eprintln!("error: world must have at least one tile");
+        process::exit(1);
+    }
+
+    let config = args.config.as_ref().map(|path| SimConfig::load(path).unwrap_or_else(|err| {
+        eprintln!("error: could not load {}: {}", path, err);
+        process::exit(1);
+    }));
+
+    let mut world = match &args.load {
+        Some(path) => World::load(path).unwrap_or_else(|err| {
+            eprintln!("error: could not load {}: {}", path, err);
+            process::exit(1);
+        }),
+        None => World::with_config(
+            size.0,
+            size.1,
+            args.seed.unwrap_or_else(rand::random),
+            config.clone().unwrap_or_default(),
+        ),
+    };
+    if let (Some(config), Some(_)) = (config, &args.load) {
+        // The grid of the loaded world follows the layout of the new config.
+        world.config = config;
+        world.reindex();
+    }
+    if let Some(nutrient) = args.nutrient {
+        world.nutrient_medium = nutrient;
+    }
+
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(BufWriter::new(File::create(path).unwrap_or_else(|err| {
+            eprintln!("error: could not create {}: {}", path, err);
+            process::exit(1);
+        }))),
+        None => Box::new(BufWriter::new(io::stdout())),
+    };
+
+    eprintln!("seed: {}", world.seed);
+    writeln!(
+        out,
+        "tick,population,ave_max_lifetime,ave_min_mass,ave_max_mass,ave_min_mass_division,ave_damage,ave_resistance"
+    ).unwrap();
+    write_info(&mut out, world.tick, &Info::from_cells(world.cells.as_slice()));
+
+    let end = world.tick + args.ticks;
+    while world.tick < end {
+        let info = world.run(args.every.min(end - world.tick));
+        write_info(&mut out, world.tick, &info);
+
+        if info.population == 0 {
+            eprintln!("population died out at tick {}", world.tick);
+            break;
+        }
+    }
+
+    out.flush().unwrap();
+
+    if let Some(path) = &args.save {
+        world.save(path).unwrap_or_else(|err| {
+            eprintln!("error: could not save {}: {}", path, err);
+            process::exit(1);
+        });
+    }
+}
+
+fn write_info(out: &mut dyn Write, tick: usize, info: &Info) {
+    writeln!(
+        out,
+        "{},{},{:.3},{:.3},{:.3},{:.3},{:.3},{:.3}",
+        tick,
+        info.population,
+        info.ave_max_lifetime,
+        info.ave_min_mass,
+        info.ave_max_mass,
+        info.ave_min_mass_division,
+        info.ave_damage,
+        info.ave_resistance,
+    ).unwrap();
+}
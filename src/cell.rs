@@ -1,6 +1,7 @@
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 
-use crate::color::ColorCell;
+use crate::{color::ColorCell, config::SimConfig, topology::Topology};
 
 pub const DEFAULT_MAX_TIME_LIFE: usize = 13;
 pub const DEFAULT_MIN_MASS: f32 = 12.0;
@@ -11,7 +12,7 @@ pub const DEFAULT_DAMAGE: f32 = 1.0;
 pub const DEFAULT_RESISTANCE: f32 = 1.0;
 pub const MAX_LEN_GENOME: usize = 10;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Cell {
     pub species: usize,
     pub position: (usize, usize),
@@ -28,11 +29,12 @@ pub struct Cell {
     pub resistance: f32,
 
     pub step: usize,
+    pub register: f32,
     pub genome: Vec<Gen>
 }
 
 impl Cell {
-    pub fn new(pos: (usize, usize)) -> Self {
+    pub fn new(pos: (usize, usize), config: &SimConfig) -> Self {
         Self {
             species: 0,
             position: pos,
@@ -40,15 +42,16 @@ impl Cell {
             direction: 0,
 
             time_life: 0,
-            max_time_life: DEFAULT_MAX_TIME_LIFE,
-            min_mass: DEFAULT_MIN_MASS,
-            mass: DEFAULT_MASS,
-            max_mass: DEFAULT_MAX_MASS,
-            min_mass_division: DEFAULT_MIN_MASS_DIVISION,
-            damage: DEFAULT_DAMAGE,
-            resistance: DEFAULT_RESISTANCE,
+            max_time_life: config.max_time_life,
+            min_mass: config.min_mass,
+            mass: config.mass,
+            max_mass: config.max_mass,
+            min_mass_division: config.min_mass_division,
+            damage: config.damage,
+            resistance: config.resistance,
 
             step: 0,
+            register: 0.0,
             genome: vec![
                 Gen::SetDirection(1),
                 Gen::Reproduce,
@@ -56,71 +59,136 @@ impl Cell {
         }
     }
 
-    pub fn to_rotate(&mut self, direction: i8) {
-        self.direction = (self.direction + direction) % 4;
+    pub fn to_rotate(&mut self, direction: i8, topology: Topology) {
+        self.direction = topology.rotate(self.direction, direction);
     }
 
-    pub fn mutate(&mut self) {
-        if rand::thread_rng().gen_range(0.0..1.0) < 0.01 {
-            self.species = rand::thread_rng().gen_range(0..1_000_000_000);
-
-            self.max_time_life = (self.max_time_life as i64 + rand::thread_rng().gen_range(-1..=1)) as usize;
-
-            self.min_mass += rand::thread_rng().gen_range(-1.0..=1.0);
-            self.min_mass_division += rand::thread_rng().gen_range(-1.0..=1.0);
-            self.max_mass += rand::thread_rng().gen_range(-1.0..=1.0);
-            self.damage += rand::thread_rng().gen_range(-1.0..=1.0);
-            self.resistance += rand::thread_rng().gen_range(-1.0..=1.0);
-
-            let rand_k = rand::thread_rng().gen_range(0..3);
+    pub fn mutate<R: Rng>(&mut self, rng: &mut R, config: &SimConfig) {
+        if rng.gen_range(0.0..1.0) < config.mutation_probability {
+            self.species = rng.gen_range(0..1_000_000_000);
+
+            let time_life = config.mutation_time_life as i64;
+            self.max_time_life = (self.max_time_life as i64 + rng.gen_range(-time_life..=time_life)).max(0) as usize;
+
+            let (mass, damage, resistance) = (
+                config.mutation_mass,
+                config.mutation_damage,
+                config.mutation_resistance,
+            );
+            self.min_mass += rng.gen_range(-mass..=mass);
+            self.min_mass_division += rng.gen_range(-mass..=mass);
+            self.max_mass += rng.gen_range(-mass..=mass);
+            self.damage += rng.gen_range(-damage..=damage);
+            self.resistance += rng.gen_range(-resistance..=resistance);
+
+            let rand_k = rng.gen_range(0..3);
             if rand_k == 0 {
                 for gen in self.genome.iter_mut() {
                     match gen {
                         Gen::SetDirection(d) => {
-                            *d += rand::thread_rng().gen_range(-1..=1);
-                        },
+                            *d = config.topology.rotate(*d, rng.gen_range(-config.mutation_direction..=config.mutation_direction));
+                        }
+                        Gen::Jump(n) | Gen::JumpIfEmptyAhead(n) | Gen::JumpIfEnemyAhead(n) => {
+                            *n = n.saturating_add(rng.gen_range(-1..=1));
+                        }
+                        Gen::JumpIfMassAbove(x, n) | Gen::JumpIfRegisterAbove(x, n) => {
+                            *x += rng.gen_range(-mass..=mass);
+                            *n = n.saturating_add(rng.gen_range(-1..=1));
+                        }
                         _ => {}
                     }
                 }
             } else if rand_k == 1 {
-                let gen_i = rand::thread_rng().gen_range(0..3);
+                // Offsets wrap around, so 1..=len reaches every gene.
+                let max_jump = self.genome.len().min(i8::MAX as usize) as i8;
+                let gen_i = rng.gen_range(0..16);
                 match gen_i {
-                    0 => self.genome.push(Gen::SetDirection(rand::thread_rng().gen_range(0..4))),
+                    0 => self.genome.push(Gen::SetDirection(rng.gen_range(0..config.topology.directions()))),
                     1 => self.genome.push(Gen::Reproduce),
                     2 => self.genome.push(Gen::Attack),
+                    3 => self.genome.push(Gen::Jump(rng.gen_range(1..=max_jump))),
+                    4 => self.genome.push(Gen::JumpIfEmptyAhead(rng.gen_range(1..=max_jump))),
+                    5 => self.genome.push(Gen::JumpIfEnemyAhead(rng.gen_range(1..=max_jump))),
+                    6 => {
+                        let x = rng.gen_range(0.0..=self.max_mass.max(0.0));
+                        self.genome.push(Gen::JumpIfMassAbove(x, rng.gen_range(1..=max_jump)));
+                    }
+                    7 => {
+                        let x = rng.gen_range(0.0..=self.max_mass.max(0.0));
+                        self.genome.push(Gen::JumpIfRegisterAbove(x, rng.gen_range(1..=max_jump)));
+                    }
+                    8 => self.genome.push(Gen::SenseEmptyAhead),
+                    9 => self.genome.push(Gen::SenseKinAhead),
+                    10 => self.genome.push(Gen::SenseForeignAhead),
+                    11 => self.genome.push(Gen::SenseNutrient),
+                    12 => self.genome.push(Gen::SenseMass),
+                    13 => self.genome.push(Gen::SenseAge),
+                    14 => self.genome.push(Gen::Move),
+                    15 => self.genome.push(Gen::Eat),
                     _ => {}
                 }
                 
-                if self.genome.len() > MAX_LEN_GENOME { self.mass = -1.0; }
+                if self.genome.len() > config.max_len_genome { self.mass = -1.0; }
             } else if rand_k == 2 {
-                let gen_i = rand::thread_rng().gen_range(0..self.genome.len());
+                let gen_i = rng.gen_range(0..self.genome.len());
                 
                 if self.genome.len() > 1 {
                     self.genome.remove(gen_i);
                 }
 
-                if self.genome.len() == 0 
+                if self.genome.is_empty() 
                 || self.damage < 0.0 
                 || self.resistance < 0.0 { 
                     self.mass = -1.0; 
                 }
             }
 
-            self.color.modify();
+            self.color.modify(rng, config.mutation_color);
+        }
+    }
+
+    pub fn feed(&mut self, food: f32, config: &SimConfig) {
+        self.time_life += 1;
+        self.step += 1;
+        self.mass += food - self.consume(config);
+
+        if self.step >= self.genome.len() {
+            self.step = 0;
+        }
+        if self.mass > self.max_mass {
+            self.mass = self.max_mass;
         }
     }
 
-    pub fn consume(&mut self) -> f32 {
-        return
-            DEFAULT_MIN_MASS / self.min_mass +
-            self.max_mass / DEFAULT_MAX_MASS +
-            self.time_life as f32 / self.max_time_life as f32;
+    pub fn consume(&self, config: &SimConfig) -> f32 {
+        config.consume_min_mass * config.min_mass / self.min_mass +
+        config.consume_max_mass * self.max_mass / config.max_mass +
+        config.consume_time_life * self.time_life as f32 / self.max_time_life as f32
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum Gen {
     SetDirection(i8),
     Reproduce,
     Attack,
+    Move,
+    Eat,
+
+    // Control flow, run in the same tick as the gene they lead to. The
+    // offsets are relative to the jump and wrap around the genome.
+    Jump(i8),
+    JumpIfEmptyAhead(i8),
+    JumpIfEnemyAhead(i8),
+    JumpIfMassAbove(f32, i8),
+    JumpIfRegisterAbove(f32, i8),
+
+    // Sensors, also free. Each one overwrites the register of the cell: 1.0 or
+    // 0.0 for the tile ahead, otherwise the value read.
+    SenseEmptyAhead,
+    SenseKinAhead,
+    SenseForeignAhead,
+    SenseNutrient,
+    SenseMass,
+    SenseAge,
 }
\ No newline at end of file
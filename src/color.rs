@@ -1,6 +1,7 @@
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct ColorCell {
     pub r: f32,
     pub g: f32,
@@ -16,10 +17,10 @@ impl ColorCell {
         }
     }
 
-    pub fn modify(&mut self) {
-        self.r += rand::thread_rng().gen_range(-0.2..=0.2);
-        self.g += rand::thread_rng().gen_range(-0.2..=0.2);
-        self.b += rand::thread_rng().gen_range(-0.2..=0.2);
+    pub fn modify<R: Rng>(&mut self, rng: &mut R, step: f32) {
+        self.r += rng.gen_range(-step..=step);
+        self.g += rng.gen_range(-step..=step);
+        self.b += rng.gen_range(-step..=step);
 
         if self.r < 0.0 { self.r = 0.0; }
         if self.g < 0.0 { self.r = 0.0; }
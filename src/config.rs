@@ -0,0 +1,259 @@
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::Path,
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cell::{
+        DEFAULT_DAMAGE, DEFAULT_MASS, DEFAULT_MAX_MASS, DEFAULT_MAX_TIME_LIFE, DEFAULT_MIN_MASS,
+        DEFAULT_MIN_MASS_DIVISION, DEFAULT_RESISTANCE, MAX_LEN_GENOME,
+    },
+    nutrient::Nutrients,
+    schedule::Schedule,
+    space::Layout,
+    topology::{Boundary, Topology},
+    update::{Conflict, UpdateMode},
+};
+
+pub const DEFAULT_MUTATION_PROBABILITY: f64 = 0.01;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SimConfig {
+    pub topology: Topology,
+    pub boundary: Boundary,
+    pub update: UpdateMode,
+    pub conflict: Conflict,
+    pub layout: Layout,
+    pub nutrients: Nutrients,
+    pub schedule: Schedule,
+
+    pub max_time_life: usize,
+    pub min_mass: f32,
+    pub mass: f32,
+    pub max_mass: f32,
+    pub min_mass_division: f32,
+    pub damage: f32,
+    pub resistance: f32,
+    pub max_len_genome: usize,
+
+    pub mutation_probability: f64,
+    pub mutation_time_life: usize,
+    pub mutation_mass: f32,
+    pub mutation_damage: f32,
+    pub mutation_resistance: f32,
+    pub mutation_direction: i8,
+    pub mutation_color: f32,
+
+    pub consume_min_mass: f32,
+    pub consume_max_mass: f32,
+    pub consume_time_life: f32,
+    pub move_cost: f32,
+
+    pub predation_efficiency: f32,
+
+    pub detritus_decay: f32,
+    // Off by default, so that configs and seeds from before detritus give
+    // the same worlds.
+    pub detritus_absorption: f32,
+    pub detritus_bite: f32,
+
+    // With finite nutrients every tile holds nutrient_capacity ticks of what
+    // the nutrient map gives. Cells eat it up, it regrows by a share of the
+    // map per tick and flows into depleted neighbours.
+    pub finite_nutrients: bool,
+    pub nutrient_capacity: f32,
+    pub nutrient_regeneration: f32,
+    pub nutrient_diffusion: f32,
+
+    // With light on, light_intensity falls into every column from the top
+    // and each cell on the way absorbs light_absorption of what reaches it,
+    // on top of what it gets from the medium.
+    pub light: bool,
+    pub light_intensity: f32,
+    pub light_absorption: f32,
+}
+
+impl SimConfig {
+    pub fn new() -> Self {
+        Self {
+            topology: Topology::VonNeumann,
+            boundary: Boundary::Clamped,
+            update: UpdateMode::Sequential,
+            conflict: Conflict::Heaviest,
+            layout: Layout::Dense,
+            nutrients: Nutrients::Gradient,
+            schedule: Schedule::Constant,
+
+            max_time_life: DEFAULT_MAX_TIME_LIFE,
+            min_mass: DEFAULT_MIN_MASS,
+            mass: DEFAULT_MASS,
+            max_mass: DEFAULT_MAX_MASS,
+            min_mass_division: DEFAULT_MIN_MASS_DIVISION,
+            damage: DEFAULT_DAMAGE,
+            resistance: DEFAULT_RESISTANCE,
+            max_len_genome: MAX_LEN_GENOME,
+
+            mutation_probability: DEFAULT_MUTATION_PROBABILITY,
+            mutation_time_life: 1,
+            mutation_mass: 1.0,
+            mutation_damage: 1.0,
+            mutation_resistance: 1.0,
+            mutation_direction: 1,
+            mutation_color: 0.2,
+
+            consume_min_mass: 1.0,
+            consume_max_mass: 1.0,
+            consume_time_life: 1.0,
+            move_cost: 1.0,
+
+            predation_efficiency: 1.0,
+
+            detritus_decay: 0.05,
+            detritus_absorption: 0.0,
+            detritus_bite: 5.0,
+
+            finite_nutrients: false,
+            nutrient_capacity: 10.0,
+            nutrient_regeneration: 0.5,
+            nutrient_diffusion: 0.1,
+
+            light: false,
+            light_intensity: 8.0,
+            light_absorption: 0.5,
+        }
+    }
+
+    pub fn neighbor(&self, size: (usize, usize), position: (usize, usize), direction: i8) -> Option<(usize, usize)> {
+        let (dx, dy) = self.topology.offset(position, direction);
+        let (mut x, y) = (position.0 as i64 + dx, position.1 as i64 + dy);
+
+        // With an odd number of rows the top and bottom rows of a hex torus
+        // are both unshifted, so the seam is shifted by half a tile instead.
+        // Crossing it upwards then moves one tile to the right, which keeps
+        // every neighbour seeing the other one back.
+        let odd_hex_torus = self.topology == Topology::Hex && self.boundary == Boundary::Torus && size.1 % 2 == 1;
+        if odd_hex_torus && y < 0 {
+            x += 1;
+        }
+
+        self.boundary.apply((x, y), size)
+    }
+
+    // Rejects values the simulation cannot run with, like empty mutation
+    // ranges or a zero max mass, which consumption divides by.
+    pub fn validate(&self) -> io::Result<()> {
+        let finite = [
+            ("min_mass", self.min_mass),
+            ("mass", self.mass),
+            ("max_mass", self.max_mass),
+            ("min_mass_division", self.min_mass_division),
+            ("damage", self.damage),
+            ("resistance", self.resistance),
+            ("mutation_mass", self.mutation_mass),
+            ("mutation_damage", self.mutation_damage),
+            ("mutation_resistance", self.mutation_resistance),
+            ("mutation_color", self.mutation_color),
+            ("consume_min_mass", self.consume_min_mass),
+            ("consume_max_mass", self.consume_max_mass),
+            ("consume_time_life", self.consume_time_life),
+            ("move_cost", self.move_cost),
+            ("detritus_bite", self.detritus_bite),
+            ("nutrient_capacity", self.nutrient_capacity),
+            ("nutrient_regeneration", self.nutrient_regeneration),
+            ("light_intensity", self.light_intensity),
+        ];
+        for (field, value) in finite {
+            check(value.is_finite(), field, "a finite number")?;
+        }
+
+        for (field, value) in [("min_mass", self.min_mass), ("max_mass", self.max_mass)] {
+            check(value > 0.0, field, "positive")?;
+        }
+
+        let non_negative = [
+            ("mutation_mass", self.mutation_mass),
+            ("mutation_damage", self.mutation_damage),
+            ("mutation_resistance", self.mutation_resistance),
+            ("mutation_color", self.mutation_color),
+            ("detritus_bite", self.detritus_bite),
+            ("nutrient_capacity", self.nutrient_capacity),
+            ("nutrient_regeneration", self.nutrient_regeneration),
+            ("light_intensity", self.light_intensity),
+        ];
+        for (field, value) in non_negative {
+            check(value >= 0.0, field, "at least 0")?;
+        }
+
+        let shares = [
+            ("predation_efficiency", self.predation_efficiency),
+            ("detritus_decay", self.detritus_decay),
+            ("detritus_absorption", self.detritus_absorption),
+            ("nutrient_diffusion", self.nutrient_diffusion),
+            ("light_absorption", self.light_absorption),
+        ];
+        for (field, value) in shares {
+            check((0.0..=1.0).contains(&value), field, "between 0 and 1")?;
+        }
+        check((0.0..=1.0).contains(&self.mutation_probability), "mutation_probability", "between 0 and 1")?;
+
+        // Life times are shifted as i64 when mutating.
+        let max_time_life = u32::MAX as usize;
+        check((1..=max_time_life).contains(&self.max_time_life), "max_time_life", "between 1 and 2^32 - 1")?;
+        check(self.mutation_time_life <= max_time_life, "mutation_time_life", "at most 2^32 - 1")?;
+        check(self.mutation_direction >= 0, "mutation_direction", "at least 0")?;
+
+        if let Nutrients::Patches { size } = self.nutrients {
+            check(size.is_finite() && size > 0.0, "nutrients size", "positive")?;
+        }
+        let schedule = match self.schedule {
+            Schedule::Constant => vec![],
+            Schedule::Seasons { amplitude, .. } => vec![amplitude],
+            Schedule::DayNight { night, .. } => vec![night],
+            Schedule::Droughts { chance, severity, .. } => vec![chance, severity],
+        };
+        check(schedule.iter().all(|value| value.is_finite()), "schedule", "made of finite numbers")
+    }
+
+    pub fn to_string(&self) -> io::Result<String> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|err| io::Error::new(ErrorKind::InvalidData, err))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_string()?)
+    }
+}
+
+impl FromStr for SimConfig {
+    type Err = io::Error;
+
+    fn from_str(config: &str) -> io::Result<Self> {
+        let config: Self = ron::de::from_str(config).map_err(|err| io::Error::new(ErrorKind::InvalidData, err))?;
+        config.validate()?;
+
+        Ok(config)
+    }
+}
+
+fn check(ok: bool, field: &str, range: &str) -> io::Result<()> {
+    if ok {
+        Ok(())
+    } else {
+        Err(io::Error::new(ErrorKind::InvalidData, format!("{} must be {}", field, range)))
+    }
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
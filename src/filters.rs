@@ -7,4 +7,5 @@ pub enum Filters {
     MinMassDivision,
     Damage,
     Resistance,
+    Nutrient,
 }
\ No newline at end of file
@@ -0,0 +1,55 @@
+use std::ops::{Index, IndexMut};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Grid<T> {
+    pub width: usize,
+    pub height: usize,
+    tiles: Vec<T>,
+}
+
+impl<T: Clone> Grid<T> {
+    pub fn new(width: usize, height: usize, value: T) -> Self {
+        Self {
+            width,
+            height,
+            tiles: vec![value; width * height],
+        }
+    }
+}
+
+impl<T> Grid<T> {
+    pub fn contains(&self, (x, y): (usize, usize)) -> bool {
+        x < self.width && y < self.height
+    }
+
+    pub fn get(&self, (x, y): (usize, usize)) -> Option<&T> {
+        if self.contains((x, y)) {
+            self.tiles.get(y * self.width + x)
+        } else {
+            None
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = ((usize, usize), &T)> {
+        let width = self.width;
+        self.tiles.iter().enumerate().map(move |(i, tile)| ((i % width, i / width), tile))
+    }
+}
+
+impl<T> Index<(usize, usize)> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, (x, y): (usize, usize)) -> &T {
+        assert!(x < self.width && y < self.height, "tile ({}, {}) is out of the grid", x, y);
+        &self.tiles[y * self.width + x]
+    }
+}
+
+impl<T> IndexMut<(usize, usize)> for Grid<T> {
+    fn index_mut(&mut self, (x, y): (usize, usize)) -> &mut T {
+        assert!(x < self.width && y < self.height, "tile ({}, {}) is out of the grid", x, y);
+        &mut self.tiles[y * self.width + x]
+    }
+}
@@ -1,4 +1,8 @@
+use crate::cell::Cell;
+
+#[derive(Clone, Copy, Debug)]
 pub struct Info {
+    pub population: usize,
     pub ave_max_lifetime: f32,
     pub ave_min_mass: f32,
     pub ave_max_mass: f32,
@@ -10,6 +14,7 @@ pub struct Info {
 impl Info {
     pub fn new() -> Self {
         Self {
+            population: 0,
             ave_max_lifetime: 0.0,
             ave_min_mass: 0.0,
             ave_max_mass: 0.0,
@@ -18,4 +23,37 @@ impl Info {
             ave_resistance: 0.0,
         }
     }
-}
\ No newline at end of file
+
+    pub fn from_cells(cells: &[Cell]) -> Self {
+        let mut info = Self::new();
+        if cells.is_empty() {
+            return info;
+        }
+
+        for cell in cells.iter() {
+            info.ave_max_lifetime += cell.max_time_life as f32;
+            info.ave_min_mass += cell.min_mass;
+            info.ave_max_mass += cell.max_mass;
+            info.ave_min_mass_division += cell.min_mass_division;
+            info.ave_damage += cell.damage;
+            info.ave_resistance += cell.resistance;
+        }
+
+        let population = cells.len() as f32;
+        info.population = cells.len();
+        info.ave_max_lifetime /= population;
+        info.ave_min_mass /= population;
+        info.ave_max_mass /= population;
+        info.ave_min_mass_division /= population;
+        info.ave_damage /= population;
+        info.ave_resistance /= population;
+
+        info
+    }
+}
+
+impl Default for Info {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,84 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+// Amounts below this are dropped, so the layer does not grow forever.
+pub const MIN_AMOUNT: f32 = 0.001;
+
+// A per-tile amount that is zero on most tiles, so only the others are kept.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Layer {
+    tiles: BTreeMap<(usize, usize), f32>,
+}
+
+impl Layer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.tiles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    pub fn get(&self, position: (usize, usize)) -> f32 {
+        self.tiles.get(&position).copied().unwrap_or(0.0)
+    }
+
+    pub fn add(&mut self, position: (usize, usize), amount: f32) {
+        if amount > 0.0 {
+            *self.tiles.entry(position).or_insert(0.0) += amount;
+        }
+    }
+
+    // Removes up to max from the tile and returns how much was taken.
+    pub fn take(&mut self, position: (usize, usize), max: f32) -> f32 {
+        let Some(amount) = self.tiles.get_mut(&position) else {
+            return 0.0;
+        };
+
+        let taken = amount.min(max.max(0.0));
+        *amount -= taken;
+        if *amount < MIN_AMOUNT {
+            self.tiles.remove(&position);
+        }
+
+        taken
+    }
+
+    pub fn set(&mut self, position: (usize, usize), amount: f32) {
+        if amount >= MIN_AMOUNT {
+            self.tiles.insert(position, amount);
+        } else {
+            self.tiles.remove(&position);
+        }
+    }
+
+    // Replaces the amount on every tile, dropping the tiles that run out.
+    pub fn update(&mut self, mut f: impl FnMut((usize, usize), f32) -> f32) {
+        self.tiles.retain(|&position, amount| {
+            *amount = f(position, *amount);
+            *amount >= MIN_AMOUNT
+        });
+    }
+
+    // Every tile loses the same share of what it holds.
+    pub fn decay(&mut self, rate: f32) {
+        let keep = 1.0 - rate.clamp(0.0, 1.0);
+        self.tiles.retain(|_, amount| {
+            *amount *= keep;
+            *amount >= MIN_AMOUNT
+        });
+    }
+
+    pub fn total(&self) -> f32 {
+        self.tiles.values().sum()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = ((usize, usize), f32)> + '_ {
+        self.tiles.iter().map(|(&position, &amount)| (position, amount))
+    }
+}
@@ -1,8 +1,21 @@
 pub mod color;
 pub mod cell;
+pub mod config;
+pub mod grid;
+pub mod layer;
+pub mod light;
+pub mod nutrient;
+pub mod schedule;
+pub mod space;
+pub mod store;
+pub mod topology;
+pub mod update;
+pub mod vm;
 pub mod world;
+pub mod snapshot;
 pub mod info;
 pub mod filters;
+#[cfg(feature = "gui")]
 pub mod settings;
 
 pub fn limit(min: i64, max: i64, n: i64) -> i64 {
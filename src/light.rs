@@ -0,0 +1,55 @@
+// Light falls into every column from the top row down. Each cell takes a
+// share of what reaches its tile and passes the rest to the tiles below.
+#[derive(Clone, Debug, Default)]
+pub struct Shade {
+    // Occupied rows of every column, bottom up.
+    columns: Vec<Vec<usize>>,
+}
+
+impl Shade {
+    pub fn new(width: usize, positions: impl Iterator<Item = (usize, usize)>) -> Self {
+        let mut columns = vec![vec![]; width];
+        for (x, y) in positions {
+            columns[x].push(y);
+        }
+        for column in &mut columns {
+            column.sort_unstable();
+        }
+
+        Self { columns }
+    }
+
+    // Keeps the shade in step with cells that are born, die or move. Without
+    // columns, that is with the light off, there is nothing to keep.
+    pub fn insert(&mut self, (x, y): (usize, usize)) {
+        if let Some(column) = self.columns.get_mut(x) {
+            let i = column.partition_point(|&row| row < y);
+            column.insert(i, y);
+        }
+    }
+
+    pub fn remove(&mut self, (x, y): (usize, usize)) {
+        if let Some(column) = self.columns.get_mut(x) {
+            if let Ok(i) = column.binary_search(&y) {
+                column.remove(i);
+            }
+        }
+    }
+
+    // How many cells stand above the tile.
+    pub fn above(&self, (x, y): (usize, usize)) -> usize {
+        let Some(column) = self.columns.get(x) else {
+            return 0;
+        };
+
+        column.len() - column.partition_point(|&row| row <= y)
+    }
+
+    // What a cell on the tile takes out of the light that entered the column.
+    pub fn absorbed(&self, position: (usize, usize), intensity: f32, absorption: f32) -> f32 {
+        let absorption = absorption.clamp(0.0, 1.0);
+        let reaching = intensity * (1.0 - absorption).powi(self.above(position) as i32);
+
+        absorption * reaching
+    }
+}
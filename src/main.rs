@@ -1,12 +1,20 @@
+use std::mem;
+
 use cells::{
-    cell::{Cell, Gen},
+    cell::Cell,
+    config::SimConfig,
     filters::Filters,
     info::Info,
-    limit,
-    world::*, settings::Settings,
+    nutrient::{Nutrients, DEFAULT_PATCH_SIZE},
+    schedule::{Schedule, DEFAULT_DAY_NIGHT, DEFAULT_DROUGHTS, DEFAULT_SEASONS},
+    snapshot::DEFAULT_SNAPSHOT_PATH,
+    space::Layout,
+    topology::{Boundary, Topology},
+    update::{Conflict, UpdateMode},
+    world::*, settings::{Settings, SIZE_CELL},
 };
 use nannou::{prelude::*, event::ElementState};
-use nannou::winit::event::WindowEvent;
+use nannou::winit::event::{VirtualKeyCode, WindowEvent};
 use nannou_egui::{
     self,
     egui,
@@ -15,6 +23,10 @@ use nannou_egui::{
 
 const WIDTH_SCREEN: u32 = 1280;
 const HEIGHT_SCREEN: u32 = 720;
+const HEX_SIZE: f32 = 1.155;
+const HEX_ROW_HEIGHT: f32 = 0.866;
+// Blocks per side at most when the nutrient map is drawn.
+const MAX_NUTRIENT_BLOCKS: usize = 128;
 
 fn main() {
     nannou::app(create_window).update(update).run();
@@ -25,6 +37,11 @@ struct Game {
     info: Info,
     filters: Filters,
     settings: Settings,
+    snapshot_path: String,
+    snapshot_status: String,
+    paused: bool,
+    steps: usize,
+    pending_steps: usize,
     egui: Egui,
 }
 
@@ -40,16 +57,39 @@ fn create_window(app: &App) -> Game {
     let window = app.window(window_id).unwrap();
 
     let egui = Egui::from_window(&window);
+    let world = world_from_args();
 
     Game {
-        world: World::new(),
+        settings: Settings::new((world.width(), world.height())),
+        world,
         info: Info::new(),
         filters: Filters::Default,
-        settings: Settings::new(),
+        snapshot_path: DEFAULT_SNAPSHOT_PATH.to_string(),
+        snapshot_status: String::new(),
+        paused: true,
+        steps: 10,
+        pending_steps: 0,
         egui,
     }
 }
 
+fn world_from_args() -> World {
+    let mut size = DEFAULT_SIZE_MAP;
+    let mut config = SimConfig::new();
+
+    let args: Vec<String> = std::env::args().collect();
+    for pair in args[1..].windows(2) {
+        match pair[0].as_str() {
+            "--width" => size.0 = pair[1].parse().expect("invalid --width"),
+            "--height" => size.1 = pair[1].parse().expect("invalid --height"),
+            "--config" => config = SimConfig::load(&pair[1]).expect("invalid --config"),
+            _ => {}
+        }
+    }
+
+    World::with_config(size.0, size.1, rand::random(), config)
+}
+
 fn raw_window_event(_app: &App, game: &mut Game, event: &nannou::winit::event::WindowEvent) {
     match event {
         WindowEvent::CloseRequested => {
@@ -82,6 +122,17 @@ fn raw_window_event(_app: &App, game: &mut Game, event: &nannou::winit::event::W
             }
             game.settings.mouse.last_pos = (position.x, position.y);
         } 
+        WindowEvent::KeyboardInput { input, .. }
+            if input.state == ElementState::Pressed && !game.egui.ctx().wants_keyboard_input() =>
+        {
+            match input.virtual_keycode {
+                Some(VirtualKeyCode::Space) => game.paused = !game.paused,
+                Some(VirtualKeyCode::Period) => game.pending_steps += 1,
+                Some(VirtualKeyCode::N) => game.pending_steps += game.steps,
+                Some(VirtualKeyCode::R) => game.world.reset(game.world.seed),
+                _ => {}
+            }
+        }
         _ => {}
     }
 
@@ -96,7 +147,29 @@ fn update(_app: &App, game: &mut Game, update: Update) {
 
     egui::Window::new("World").show(&ctx, |ui| {
         ui.label(format!(
-            "Cells: {}", game.world.cells.1.len()
+            "Cells: {}", game.info.population
+        ));
+
+        ui.label(format!(
+            "Seed: {}", game.world.seed
+        ));
+
+        ui.label(format!(
+            "Size: {}x{}", game.world.width(), game.world.height()
+        ));
+
+        ui.label(format!(
+            "Detritus: {:.1}", game.world.detritus.total()
+        ));
+
+        ui.label(format!(
+            "Nutrients eaten: {:.1}", game.world.nutrient_deficit.total()
+        ));
+
+        ui.label(format!(
+            "Phase: {} (x{:.2})",
+            game.world.config.schedule.phase(game.world.tick, game.world.seed),
+            game.world.supply()
         ));
 
         ui.label(format!(
@@ -115,6 +188,63 @@ fn update(_app: &App, game: &mut Game, update: Update) {
             &mut game.world.speed,
             0..=50,
         ));
+
+        ui.separator();
+        ui.label(format!(
+            "Tick: {} ({})",
+            game.world.tick,
+            if game.paused { "paused" } else { "running" }
+        ));
+        ui.horizontal(|ui| {
+            if ui.button(if game.paused { "Resume [Space]" } else { "Pause [Space]" }).clicked() {
+                game.paused = !game.paused;
+            }
+
+            if ui.button("Step 1 tick [.]").clicked() {
+                game.pending_steps += 1;
+            }
+        });
+        ui.horizontal(|ui| {
+            if ui.button(format!("Step {} ticks [N]", game.steps)).clicked() {
+                game.pending_steps += game.steps;
+            }
+            ui.add(egui::DragValue::new(&mut game.steps).clamp_range(1..=10_000));
+        });
+        ui.horizontal(|ui| {
+            if ui.button("Reset world [R]").clicked() {
+                game.world.reset(game.world.seed);
+            }
+
+            if ui.button("Reset with new seed").clicked() {
+                game.world.reset(rand::random());
+            }
+        });
+
+        ui.separator();
+        ui.label("Snapshot:");
+        ui.text_edit_singleline(&mut game.snapshot_path);
+        ui.horizontal(|ui| {
+            if ui.button("Save").clicked() {
+                game.snapshot_status = match game.world.save(&game.snapshot_path) {
+                    Ok(()) => format!("Saved at tick {}", game.world.tick),
+                    Err(err) => format!("Save failed: {}", err),
+                };
+            }
+
+            if ui.button("Load").clicked() {
+                game.snapshot_status = match World::load(&game.snapshot_path) {
+                    Ok(world) => {
+                        game.settings = Settings::new((world.width(), world.height()));
+                        game.world = world;
+                        format!("Loaded tick {}", game.world.tick)
+                    }
+                    Err(err) => format!("Load failed: {}", err),
+                };
+            }
+        });
+        if !game.snapshot_status.is_empty() {
+            ui.label(&game.snapshot_status);
+        }
     });
 
     egui::Window::new("Info").show(&ctx, |ui| {
@@ -191,182 +321,183 @@ fn update(_app: &App, game: &mut Game, update: Update) {
             Filters::Resistance,
             "Resistance.",
         );
-    });
-
-    for _ in 0..game.world.speed {
-
-        let mut new_buf_cells: Vec<Cell> = vec![];
-        for i in 0..game.world.cells.1.len() {
-        if i >= game.world.cells.1.len() {
-            break;
-        }
 
-        if game.world.cells.1[i].step >= game.world.cells.1[i].genome.len() {
-            game.world.cells.1[i].step = 0;
-        }
-        match game.world.cells.1[i].genome[game.world.cells.1[i].step] {
-            Gen::SetDirection(d) => game.world.cells.1[i].to_rotate(d),
-            Gen::Reproduce => {
-                let mut cell = &mut game.world.cells.1[i];
-                let grid = &mut game.world.cells.0;
-
-                let mut new_cell = cell.clone();
-                let mut is_rprdc = false;
-                let (left, right, up, down) = (
-                    limit(0, (SIZE_MAP.0 - 1) as i64, cell.position.0 as i64 - 1) as usize,
-                    limit(0, (SIZE_MAP.0 - 1) as i64, cell.position.0 as i64 + 1) as usize,
-                    limit(0, (SIZE_MAP.1 - 1) as i64, cell.position.1 as i64 + 1) as usize,
-                    limit(0, (SIZE_MAP.1 - 1) as i64, cell.position.1 as i64 - 1) as usize,
-                );
+        ui.radio_value(
+            &mut game.filters,
+            Filters::Nutrient,
+            "Nutrient.",
+        );
+    });
 
-                match cell.direction {
-                    0 => {
-                        if grid[right][cell.position.1] < 0 {
-                            is_rprdc = true;
-                            new_cell.position.0 = right;
-                        }
-                    }
-                    1 => {
-                        if grid[cell.position.0][up] < 0 {
-                            is_rprdc = true;
-                            new_cell.position.1 = up;
-                        }
-                    }
-                    2 => {
-                        if grid[left][cell.position.1] < 0 {
-                            is_rprdc = true;
-                            new_cell.position.0 = left;
-                        }
-                    }
-                    3 => {
-                        if grid[cell.position.0][down] < 0 {
-                            is_rprdc = true;
-                            new_cell.position.1 = down;
-                        }
-                    }
-                    _ => {}
+    egui::Window::new("Parameters").show(&ctx, |ui| {
+        let config = &mut game.world.config;
+
+        ui.collapsing("World", |ui| {
+            ui.label("Topology:");
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut config.topology, Topology::VonNeumann, "Von Neumann");
+                ui.radio_value(&mut config.topology, Topology::Moore, "Moore");
+                ui.radio_value(&mut config.topology, Topology::Hex, "Hex");
+            });
+
+            ui.label("Boundary:");
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut config.boundary, Boundary::Clamped, "Clamped");
+                ui.radio_value(&mut config.boundary, Boundary::Walls, "Walls");
+                ui.radio_value(&mut config.boundary, Boundary::Torus, "Torus");
+            });
+
+            ui.label("Update:");
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut config.update, UpdateMode::Sequential, "Sequential");
+                ui.radio_value(&mut config.update, UpdateMode::Synchronous, "Synchronous");
+                ui.radio_value(&mut config.update, UpdateMode::Parallel, "Parallel");
+            });
+
+            ui.label("Conflicting births:");
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut config.conflict, Conflict::Heaviest, "Heaviest wins");
+                ui.radio_value(&mut config.conflict, Conflict::Cancel, "All fail");
+            });
+
+            ui.label("Grid:");
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut config.layout, Layout::Dense, "Dense");
+                ui.radio_value(&mut config.layout, Layout::Chunked, "Chunked");
+            });
+
+            ui.label("Nutrients:");
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut config.nutrients, Nutrients::Gradient, "Gradient");
+                ui.radio_value(&mut config.nutrients, Nutrients::Radial, "Radial");
+                let patches = matches!(config.nutrients, Nutrients::Patches { .. });
+                if ui.radio(patches, "Patches").clicked() && !patches {
+                    config.nutrients = Nutrients::Patches { size: DEFAULT_PATCH_SIZE };
                 }
+                ui.radio_value(&mut config.nutrients, Nutrients::Uniform, "Uniform");
+            });
+            if let Nutrients::Patches { size } = &mut config.nutrients {
+                parameter(ui, "Patch size:", size, 1.0..=100.0);
+            }
 
-                if is_rprdc && cell.mass > cell.min_mass_division {
-                    new_cell.step = 0;
-                    grid[new_cell.position.0][new_cell.position.1] = i as i32;
-                    (cell.time_life, new_cell.time_life) = (0, 0);
-                    (cell.mass, new_cell.mass) = (cell.mass / 2.0, new_cell.mass / 2.0);
-                    new_cell.mutate();
-                    new_buf_cells.push(new_cell);
-                }
+            ui.checkbox(&mut config.finite_nutrients, "Finite nutrients");
+            if config.finite_nutrients {
+                parameter(ui, "Stock in ticks:", &mut config.nutrient_capacity, 1.0..=100.0);
+                parameter(ui, "Regrowth per tick:", &mut config.nutrient_regeneration, 0.0..=1.0);
+                parameter(ui, "Diffusion per tick:", &mut config.nutrient_diffusion, 0.0..=1.0);
             }
-            Gen::Attack => {
-                let (left, right, up, down) = (
-                    limit(
-                        0, (SIZE_MAP.0 - 1) as i64, 
-                        game.world.cells.1[i].position.0 as i64 - 1
-                    ) as usize,
-                    limit(
-                        0, (SIZE_MAP.0 - 1) as i64, 
-                        game.world.cells.1[i].position.0 as i64 + 1
-                    ) as usize,
-                    limit(
-                        0, (SIZE_MAP.1 - 1) as i64, 
-                        game.world.cells.1[i].position.1 as i64 + 1
-                    ) as usize,
-                    limit(
-                        0, (SIZE_MAP.1 - 1) as i64, 
-                        game.world.cells.1[i].position.1 as i64 - 1
-                    ) as usize,
-                );
-                
-                let grid = game.world.cells.0;
-                let mut i_neighbor_cell = grid[right][game.world.cells.1[i].position.1] as usize;
-                match game.world.cells.1[i].direction {
-                    0 => {
-                        if grid[right][game.world.cells.1[i].position.1] > -1 {
-                            i_neighbor_cell = grid[right][game.world.cells.1[i].position.1] as usize;
-                        }
-                    }
-                    1 => {
-                        if grid[game.world.cells.1[i].position.0][up] > -1 {
-                            i_neighbor_cell = grid[game.world.cells.1[i].position.0][up] as usize;
-                        }
-                    }
-                    2 => {
-                        if grid[left][game.world.cells.1[i].position.1] > -1 {
-                            i_neighbor_cell = grid[left][game.world.cells.1[i].position.1]  as usize;
-                        }
+        });
+
+        ui.collapsing("Newborn cell", |ui| {
+            parameter(ui, "Max time of life:", &mut config.max_time_life, 1..=100);
+            // Consumption divides by both, see SimConfig::validate.
+            parameter(ui, "Min mass:", &mut config.min_mass, 0.1..=100.0);
+            parameter(ui, "Mass:", &mut config.mass, 0.0..=100.0);
+            parameter(ui, "Max mass:", &mut config.max_mass, 0.1..=100.0);
+            parameter(ui, "Min mass of division:", &mut config.min_mass_division, 0.0..=100.0);
+            parameter(ui, "Damage:", &mut config.damage, 0.0..=10.0);
+            parameter(ui, "Resistance:", &mut config.resistance, 0.0..=10.0);
+        });
+
+        ui.collapsing("Mutation", |ui| {
+            parameter(ui, "Probability:", &mut config.mutation_probability, 0.0..=1.0);
+            parameter(ui, "Max genome length:", &mut config.max_len_genome, 1..=100);
+            parameter(ui, "Time of life step:", &mut config.mutation_time_life, 0..=10);
+            parameter(ui, "Mass step:", &mut config.mutation_mass, 0.0..=10.0);
+            parameter(ui, "Damage step:", &mut config.mutation_damage, 0.0..=10.0);
+            parameter(ui, "Resistance step:", &mut config.mutation_resistance, 0.0..=10.0);
+            parameter(ui, "Direction step:", &mut config.mutation_direction, 0..=3);
+            parameter(ui, "Color step:", &mut config.mutation_color, 0.0..=1.0);
+        });
+
+        ui.collapsing("Consumption", |ui| {
+            parameter(ui, "Min mass weight:", &mut config.consume_min_mass, 0.0..=5.0);
+            parameter(ui, "Max mass weight:", &mut config.consume_max_mass, 0.0..=5.0);
+            parameter(ui, "Time of life weight:", &mut config.consume_time_life, 0.0..=5.0);
+            parameter(ui, "Move cost:", &mut config.move_cost, 0.0..=10.0);
+        });
+
+        ui.collapsing("Predation", |ui| {
+            parameter(ui, "Share of damage eaten:", &mut config.predation_efficiency, 0.0..=1.0);
+        });
+
+        ui.collapsing("Detritus", |ui| {
+            parameter(ui, "Decay per tick:", &mut config.detritus_decay, 0.0..=1.0);
+            parameter(ui, "Absorbed per tick:", &mut config.detritus_absorption, 0.0..=1.0);
+            parameter(ui, "Eaten per bite:", &mut config.detritus_bite, 0.0..=50.0);
+        });
+
+        ui.collapsing("Light", |ui| {
+            ui.checkbox(&mut config.light, "Light from the top");
+            parameter(ui, "Intensity:", &mut config.light_intensity, 0.0..=20.0);
+            parameter(ui, "Absorbed per cell:", &mut config.light_absorption, 0.0..=1.0);
+        });
+
+        ui.collapsing("Environment", |ui| {
+            let schedules = [
+                (Schedule::Constant, "Constant"),
+                (DEFAULT_SEASONS, "Seasons"),
+                (DEFAULT_DAY_NIGHT, "Day and night"),
+                (DEFAULT_DROUGHTS, "Droughts"),
+            ];
+            ui.horizontal(|ui| {
+                for (schedule, name) in schedules {
+                    let selected = mem::discriminant(&config.schedule) == mem::discriminant(&schedule);
+                    if ui.radio(selected, name).clicked() && !selected {
+                        config.schedule = schedule;
                     }
-                    3 => {
-                        if grid[game.world.cells.1[i].position.0][down] > -1 {
-                            i_neighbor_cell = grid[game.world.cells.1[i].position.0][down] as usize;
-                        }
-                    }
-                    _ => {}
                 }
+            });
 
-                if i_neighbor_cell < game.world.cells.1.len() && 
-                game.world.cells.1[i].species != game.world.cells.1[i_neighbor_cell].species {
-                    let dmg = game.world.cells.1[i_neighbor_cell].resistance - game.world.cells.1[i].damage;
-                    
-                    if dmg < 0.0 {
-                        game.world.cells.1[i_neighbor_cell].mass += dmg;
-                    }
-
-                    game.world.cells.1[i].mass += game.world.cells.1[i].damage - game.world.cells.1[i_neighbor_cell].resistance;
+            match &mut config.schedule {
+                Schedule::Constant => {}
+                Schedule::Seasons { period, amplitude } => {
+                    parameter(ui, "Year in ticks:", period, 2..=5000);
+                    parameter(ui, "Amplitude:", amplitude, 0.0..=1.0);
+                }
+                Schedule::DayNight { period, night } => {
+                    parameter(ui, "Day in ticks:", period, 2..=1000);
+                    parameter(ui, "Supply at night:", night, 0.0..=1.0);
+                }
+                Schedule::Droughts { length, chance, severity } => {
+                    parameter(ui, "Spell in ticks:", length, 1..=2000);
+                    parameter(ui, "Drought chance:", chance, 0.0..=1.0);
+                    parameter(ui, "Severity:", severity, 0.0..=1.0);
                 }
             }
-        }
-
-        {
-            let mut cell = &mut game.world.cells.1[i];
-
-            cell.time_life += 1;
-            cell.step += 1;
-            cell.mass += game.world.nutrient_medium
-                * (1.0 - cell.position.1 as f32 / SIZE_MAP.1 as f32)
-                - cell.consume();
-
-            if cell.step >= cell.genome.len() {
-                cell.step = 0;
-            }
-            if cell.mass > cell.max_mass {
-                cell.mass = cell.max_mass;
-            }
+        });
 
-            game.info.ave_max_lifetime += cell.max_time_life as f32;
-            game.info.ave_min_mass += cell.min_mass;
-            game.info.ave_max_mass += cell.max_mass;
-            game.info.ave_min_mass_division += cell.min_mass_division;
-            game.info.ave_damage += cell.damage;
-            game.info.ave_resistance += cell.resistance;
+        if ui.button("Reset to defaults").clicked() {
+            *config = SimConfig::new();
         }
+    });
 
-        if i < game.world.cells.1.len()
-            && (game.world.cells.1[i].time_life > game.world.cells.1[i].max_time_life
-                || game.world.cells.1[i].mass < game.world.cells.1[i].min_mass)
-        {
-            let (x, y) = (
-                game.world.cells.1[i].position.0,
-                game.world.cells.1[i].position.1,
-            );
-
-            game.world.cells.0[x][y] = -1;
-            game.world.cells.1.remove(i);
-        }
+    if game.world.config.layout != game.world.grid.layout() {
+        game.world.reindex();
     }
-        game.info.ave_max_lifetime /= game.world.cells.1.len() as f32;
-        game.info.ave_min_mass /= game.world.cells.1.len() as f32;
-        game.info.ave_max_mass /= game.world.cells.1.len() as f32;
-        game.info.ave_min_mass_division /= game.world.cells.1.len() as f32;
-        game.info.ave_damage /= game.world.cells.1.len() as f32;
-        game.info.ave_resistance /= game.world.cells.1.len() as f32;
 
-        game.world.cells.1.append(&mut new_buf_cells);
+    let ticks = if game.paused { 0 } else { game.world.speed } + game.pending_steps;
+    game.pending_steps = 0;
+    game.info = game.world.run(ticks);
+}
 
-    }
+fn parameter<Num: egui::emath::Numeric>(
+    ui: &mut egui::Ui,
+    label: &str,
+    value: &mut Num,
+    range: std::ops::RangeInclusive<Num>,
+) {
+    ui.label(label);
+    ui.add(egui::Slider::new(value, range));
 }
 
 fn view(app: &App, game: &Game, frame: Frame) {
     let settings = &game.settings;
-    let size_cell = (5.0 * settings.scale, 5.0 * settings.scale);
+    let size_cell = (SIZE_CELL * settings.scale, SIZE_CELL * settings.scale);
+    let size_map = (
+        game.world.width() as f32,
+        game.world.height() as f32 * row_height(game),
+    );
     let draw = app.draw();
 
     draw.background().rgb(15. / 255., 15. / 255., 25. / 255.);
@@ -375,71 +506,158 @@ fn view(app: &App, game: &Game, frame: Frame) {
         .stroke_weight(size_cell.0)
         .color(rgba(0.0, 0.0, 0.0, 0.0))
         .w_h(
-            SIZE_MAP.0 as f32 * size_cell.0 + size_cell.0, 
-            SIZE_MAP.1 as f32 * size_cell.1 + size_cell.1
+            size_map.0 * size_cell.0 + size_cell.0, 
+            size_map.1 * size_cell.1 + size_cell.1
         )
         .x_y(
-            SIZE_MAP.0 as f32 * size_cell.0 / 2.0 + settings.position.x as f32 - size_cell.0 / 2.0, 
-            SIZE_MAP.1 as f32 * size_cell.1 / 2.0 + settings.position.y as f32 - size_cell.1 / 2.0
+            size_map.0 * size_cell.0 / 2.0 + settings.position.x - size_cell.0 / 2.0, 
+            size_map.1 * size_cell.1 / 2.0 + settings.position.y - size_cell.1 / 2.0
         );
 
-    for cell in game.world.cells.1.iter() {
-        let rect = draw
-            .rect()
-            .w(size_cell.0)
-            .h(size_cell.1)
-            .x(cell.position.0 as f32 * size_cell.0 + settings.position.x as f32)
-            .y(cell.position.1 as f32 * size_cell.1 + settings.position.y as f32);
+    let (min, max) = visible_tiles(app, game, size_cell);
+    if game.filters == Filters::Nutrient {
+        draw_nutrients(&draw, game, min, max, size_cell);
+    }
 
-        match game.filters {
+    let visible: Vec<&Cell> = match game.world.grid.layout() {
+        Layout::Chunked => game.world.grid.occupied(min, max)
+            .into_iter()
+            .filter_map(|(_, id)| game.world.cells.get(id))
+            .collect(),
+        Layout::Dense => game.world.cells.as_slice()
+            .iter()
+            .filter(|cell| {
+                let (x, y) = cell.position;
+                x >= min.0 && x < max.0 && y >= min.1 && y < max.1
+            })
+            .collect(),
+    };
+
+    for cell in visible {
+        let (r, g, b) = match game.filters {
             Filters::MaxLifeTime => {
-                rect.rgb(
+                (
                     cell.max_time_life as f32 / game.info.ave_max_lifetime - 0.8,
                     cell.max_time_life as f32 / game.info.ave_max_lifetime - 0.8,
                     cell.max_time_life as f32 / game.info.ave_max_lifetime - 0.8,
-                );
+                )
             }
             Filters::MaxMass => {
-                rect.rgb(
-                    cell.max_mass as f32 / game.info.ave_max_mass - 0.8,
-                    cell.max_mass as f32 / game.info.ave_max_mass - 0.8,
-                    cell.max_mass as f32 / game.info.ave_max_mass - 0.8,
-                );
+                (
+                    cell.max_mass / game.info.ave_max_mass - 0.8,
+                    cell.max_mass / game.info.ave_max_mass - 0.8,
+                    cell.max_mass / game.info.ave_max_mass - 0.8,
+                )
             }
             Filters::MinMass => {
-                rect.rgb(
-                    cell.min_mass as f32 / game.info.ave_min_mass - 0.8,
-                    cell.min_mass as f32 / game.info.ave_min_mass - 0.8,
-                    cell.min_mass as f32 / game.info.ave_min_mass - 0.8,
-                );
+                (
+                    cell.min_mass / game.info.ave_min_mass - 0.8,
+                    cell.min_mass / game.info.ave_min_mass - 0.8,
+                    cell.min_mass / game.info.ave_min_mass - 0.8,
+                )
             }
             Filters::MinMassDivision => {
-                rect.rgb(
-                    cell.min_mass_division as f32 / game.info.ave_min_mass_division - 0.8,
-                    cell.min_mass_division as f32 / game.info.ave_min_mass_division - 0.8,
-                    cell.min_mass_division as f32 / game.info.ave_min_mass_division - 0.8,
-                );
+                (
+                    cell.min_mass_division / game.info.ave_min_mass_division - 0.8,
+                    cell.min_mass_division / game.info.ave_min_mass_division - 0.8,
+                    cell.min_mass_division / game.info.ave_min_mass_division - 0.8,
+                )
             }
             Filters::Damage => {
-                rect.rgb(
-                    cell.damage as f32 / game.info.ave_damage - 0.8,
-                    cell.damage as f32 / game.info.ave_damage - 0.8,
-                    cell.damage as f32 / game.info.ave_damage - 0.8,
-                );
+                (
+                    cell.damage / game.info.ave_damage - 0.8,
+                    cell.damage / game.info.ave_damage - 0.8,
+                    cell.damage / game.info.ave_damage - 0.8,
+                )
             }
             Filters::Resistance => {
-                rect.rgb(
-                    cell.resistance as f32 / game.info.ave_resistance - 0.8,
-                    cell.resistance as f32 / game.info.ave_resistance - 0.8,
-                    cell.resistance as f32 / game.info.ave_resistance - 0.8,
-                );
+                (
+                    cell.resistance / game.info.ave_resistance - 0.8,
+                    cell.resistance / game.info.ave_resistance - 0.8,
+                    cell.resistance / game.info.ave_resistance - 0.8,
+                )
             }
             _ => {
-                rect.rgb(cell.color.r, cell.color.g, cell.color.b);
+                (cell.color.r, cell.color.g, cell.color.b)
+            }
+        };
+
+        let (x, y) = tile_to_screen(game, cell.position, size_cell);
+        match game.world.config.topology {
+            Topology::Hex => {
+                draw.ellipse()
+                    .resolution(6.0)
+                    .z_radians(PI / 6.0)
+                    .w_h(size_cell.0 * HEX_SIZE, size_cell.1 * HEX_SIZE)
+                    .x_y(x, y)
+                    .rgb(r, g, b);
+            }
+            _ => {
+                draw.rect()
+                    .w(size_cell.0)
+                    .h(size_cell.1)
+                    .x_y(x, y)
+                    .rgb(r, g, b);
             }
         }
     }
 
     draw.to_frame(app, &frame).unwrap();
     game.egui.draw_to_frame(&frame).unwrap();
-}
\ No newline at end of file
+}
+
+fn row_height(game: &Game) -> f32 {
+    match game.world.config.topology {
+        Topology::Hex => HEX_ROW_HEIGHT,
+        _ => 1.0,
+    }
+}
+
+// One rect per block of tiles, so that a large zoomed out world stays cheap.
+fn draw_nutrients(draw: &Draw, game: &Game, min: (usize, usize), max: (usize, usize), size_cell: (f32, f32)) {
+    let world = &game.world;
+    let max = (max.0.min(world.width()), max.1.min(world.height()));
+    let span = max.0.saturating_sub(min.0).max(max.1.saturating_sub(min.1));
+    let block = (span / MAX_NUTRIENT_BLOCKS).max(1);
+    let size = (block as f32 * size_cell.0, block as f32 * row_height(game) * size_cell.1);
+
+    for y in (min.1..max.1).step_by(block) {
+        for x in (min.0..max.0).step_by(block) {
+            let mut value = world.config.nutrients.sample((x, y), (world.width(), world.height()), world.seed);
+            if world.config.finite_nutrients && world.capacity((x, y)) > 0.0 {
+                value *= world.stock((x, y)) / world.capacity((x, y));
+            }
+            let (sx, sy) = tile_to_screen(game, (x, y), size_cell);
+
+            draw.rect()
+                .w_h(size.0, size.1)
+                .x_y(sx + (size.0 - size_cell.0) / 2.0, sy + (size.1 - size_cell.1 * row_height(game)) / 2.0)
+                .rgb(0.0, value * 0.5, value * 0.25);
+        }
+    }
+}
+
+// Tiles in [min, max) that can be on screen, with a tile of margin.
+fn visible_tiles(app: &App, game: &Game, size_cell: (f32, f32)) -> ((usize, usize), (usize, usize)) {
+    let rect = app.window_rect();
+    let (x, y) = (game.settings.position.x, game.settings.position.y);
+    let row = row_height(game) * size_cell.1;
+
+    let tile = |screen: f32, offset: f32, size: f32| ((screen - offset) / size).max(0.0) as usize;
+    (
+        (tile(rect.left(), x, size_cell.0).saturating_sub(1), tile(rect.bottom(), y, row).saturating_sub(1)),
+        (tile(rect.right(), x, size_cell.0) + 2, tile(rect.top(), y, row) + 2),
+    )
+}
+
+fn tile_to_screen(game: &Game, position: (usize, usize), size_cell: (f32, f32)) -> (f32, f32) {
+    let shift = match game.world.config.topology {
+        Topology::Hex if position.1 % 2 == 1 => 0.5,
+        _ => 0.0,
+    };
+
+    (
+        (position.0 as f32 + shift) * size_cell.0 + game.settings.position.x,
+        position.1 as f32 * row_height(game) * size_cell.1 + game.settings.position.y,
+    )
+}
@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_PATCH_SIZE: f32 = 12.0;
+
+// How the nutrients of the medium are spread over the world, from 0.0 to 1.0
+// on every tile. World::nutrient_medium scales it. The map is computed per
+// tile when asked, so it costs no memory however large the world is.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Nutrients {
+    // Richest at the bottom row, falling off with height.
+    #[default]
+    Gradient,
+    // Richest in the middle, nothing in the corners.
+    Radial,
+    // Smooth random patches about size tiles across, fixed by the seed.
+    Patches { size: f32 },
+    Uniform,
+}
+
+impl Nutrients {
+    pub fn sample(&self, (x, y): (usize, usize), (width, height): (usize, usize), seed: u64) -> f32 {
+        match *self {
+            Nutrients::Gradient => 1.0 - y as f32 / height as f32,
+            Nutrients::Radial => {
+                let center = (width as f32 / 2.0, height as f32 / 2.0);
+                let (dx, dy) = (x as f32 + 0.5 - center.0, y as f32 + 0.5 - center.1);
+
+                (1.0 - dx.hypot(dy) / center.0.hypot(center.1)).max(0.0)
+            }
+            Nutrients::Patches { size } => {
+                let size = size.max(1.0);
+                value_noise(x as f32 / size, y as f32 / size, seed)
+            }
+            Nutrients::Uniform => 1.0,
+        }
+    }
+
+    // A tile with the most nutrients, where the first cell of a world starts.
+    pub fn richest(&self, (width, height): (usize, usize), seed: u64) -> (usize, usize) {
+        match *self {
+            Nutrients::Gradient | Nutrients::Uniform => (0, 0),
+            Nutrients::Radial => (width / 2, height / 2),
+            // Noise between lattice points is a blend of them, so the best
+            // lattice point is as good as any tile.
+            Nutrients::Patches { size } => {
+                let size = size.max(1.0);
+                let lattice = |n: usize| (0..).map(move |i| (i as f32 * size) as usize).take_while(move |&t| t < n);
+
+                let mut best = ((0, 0), f32::MIN);
+                for y in lattice(height) {
+                    for x in lattice(width) {
+                        let value = self.sample((x, y), (width, height), seed);
+                        if value > best.1 {
+                            best = ((x, y), value);
+                        }
+                    }
+                }
+
+                best.0
+            }
+        }
+    }
+}
+
+// Random values on a lattice, smoothly blended in between.
+fn value_noise(x: f32, y: f32, seed: u64) -> f32 {
+    let (ix, iy) = (x.floor() as i64, y.floor() as i64);
+    let (fx, fy) = (smooth(x - x.floor()), smooth(y - y.floor()));
+    let corner = |dx: i64, dy: i64| lattice(ix + dx, iy + dy, seed);
+
+    let bottom = lerp(corner(0, 0), corner(1, 0), fx);
+    let top = lerp(corner(0, 1), corner(1, 1), fx);
+    lerp(bottom, top, fy)
+}
+
+// splitmix64 of the point and the seed, as a value in [0, 1).
+pub(crate) fn lattice(x: i64, y: i64, seed: u64) -> f32 {
+    let mut z = seed
+        ^ (x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (y as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+
+    (z >> 40) as f32 / (1u64 << 24) as f32
+}
+
+fn smooth(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
@@ -0,0 +1,68 @@
+use std::f32::consts::TAU;
+
+use serde::{Deserialize, Serialize};
+
+use crate::nutrient::lattice;
+
+pub const DEFAULT_SEASONS: Schedule = Schedule::Seasons { period: 500, amplitude: 0.5 };
+pub const DEFAULT_DAY_NIGHT: Schedule = Schedule::DayNight { period: 20, night: 0.5 };
+pub const DEFAULT_DROUGHTS: Schedule = Schedule::Droughts { length: 200, chance: 0.2, severity: 0.5 };
+
+// How the supply of the medium and the light changes over ticks, as a factor
+// of them. It only depends on the tick and the seed, so a loaded snapshot
+// carries on in the same phase.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Schedule {
+    #[default]
+    Constant,
+    // A sine around 1.0 of the given amplitude, starting with spring.
+    Seasons { period: usize, amplitude: f32 },
+    // The first half of the period is a day with the full supply, the second
+    // one a night with the night share of it.
+    DayNight { period: usize, night: f32 },
+    // Ticks are split into spells of the given length, each of them a drought
+    // by chance, which cuts the supply by severity.
+    Droughts { length: usize, chance: f32, severity: f32 },
+}
+
+impl Schedule {
+    pub fn factor(&self, tick: usize, seed: u64) -> f32 {
+        match *self {
+            Schedule::Constant => 1.0,
+            Schedule::Seasons { period, amplitude } => {
+                let angle = TAU * (tick % period.max(1)) as f32 / period.max(1) as f32;
+                (1.0 + amplitude * angle.sin()).max(0.0)
+            }
+            Schedule::DayNight { period, night } if is_night(tick, period) => night.max(0.0),
+            Schedule::DayNight { .. } => 1.0,
+            Schedule::Droughts { length, chance, severity } if is_drought(tick, length, chance, seed) => {
+                1.0 - severity.clamp(0.0, 1.0)
+            }
+            Schedule::Droughts { .. } => 1.0,
+        }
+    }
+
+    pub fn phase(&self, tick: usize, seed: u64) -> &'static str {
+        match *self {
+            Schedule::Constant => "constant",
+            Schedule::Seasons { period, .. } => {
+                ["spring", "summer", "autumn", "winter"][(tick % period.max(1)) * 4 / period.max(1)]
+            }
+            Schedule::DayNight { period, .. } if is_night(tick, period) => "night",
+            Schedule::DayNight { .. } => "day",
+            Schedule::Droughts { length, chance, .. } if is_drought(tick, length, chance, seed) => "drought",
+            Schedule::Droughts { .. } => "normal",
+        }
+    }
+}
+
+fn is_night(tick: usize, period: usize) -> bool {
+    let period = period.max(2);
+    tick % period >= period / 2
+}
+
+fn is_drought(tick: usize, length: usize, chance: f32, seed: u64) -> bool {
+    let spell = tick / length.max(1);
+    // Another seed than the nutrient patches, so droughts do not follow them.
+    lattice(spell as i64, 0, !seed) < chance
+}
@@ -1,6 +1,6 @@
 use nannou::prelude::Vec2;
 
-use crate::world::SIZE_MAP;
+pub const SIZE_CELL: f32 = 5.0;
 
 pub struct Settings {
     pub scale: f32,
@@ -9,10 +9,13 @@ pub struct Settings {
 }
 
 impl Settings {
-    pub fn new() -> Self {
+    pub fn new(size_map: (usize, usize)) -> Self {
         Self {
             scale: 1.0,
-            position: Vec2::new(SIZE_MAP.0 as f32, SIZE_MAP.1 as f32),
+            position: Vec2::new(
+                -(size_map.0 as f32) * SIZE_CELL / 2.0,
+                -(size_map.1 as f32) * SIZE_CELL / 2.0,
+            ),
             mouse: Mouse::new(),
         }
     }
@@ -31,4 +34,10 @@ impl Mouse {
             last_pos: (0.0, 0.0)
         }
     }
-}
\ No newline at end of file
+}
+
+impl Default for Mouse {
+    fn default() -> Self {
+        Self::new()
+    }
+}
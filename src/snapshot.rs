@@ -0,0 +1,111 @@
+use std::{
+    collections::HashSet,
+    fs,
+    io::{self, ErrorKind},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::world::World;
+
+pub const DEFAULT_SNAPSHOT_PATH: &str = "world.ron";
+
+// Bump together with a new entry in MIGRATIONS whenever a change of the saved
+// World, Cell or Gen is not covered by serde alone. New fields get
+// #[serde(default)], renamed Gen variants get #[serde(alias = "...")],
+// everything else is fixed up by the migration.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+// The entry at index N - 1 turns a version N world into a version N + 1 one.
+const MIGRATIONS: [fn(&mut World); SNAPSHOT_VERSION as usize - 1] = [];
+
+#[derive(Serialize)]
+struct SnapshotRef<'a> {
+    version: u32,
+    world: &'a World,
+}
+
+#[derive(Deserialize)]
+struct Snapshot {
+    world: World,
+}
+
+#[derive(Deserialize)]
+struct Header {
+    version: u32,
+}
+
+pub fn to_string(world: &World) -> io::Result<String> {
+    let snapshot = SnapshotRef {
+        version: SNAPSHOT_VERSION,
+        world,
+    };
+
+    ron::ser::to_string(&snapshot).map_err(|err| io::Error::new(ErrorKind::InvalidData, err))
+}
+
+pub fn from_str(snapshot: &str) -> io::Result<World> {
+    let invalid = |err: ron::error::SpannedError| io::Error::new(ErrorKind::InvalidData, err);
+
+    let version = ron::de::from_str::<Header>(snapshot).map_err(invalid)?.version;
+    if version == 0 || version > SNAPSHOT_VERSION {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!("snapshot version {} is not supported, the latest is {}", version, SNAPSHOT_VERSION),
+        ));
+    }
+
+    let mut world = ron::de::from_str::<Snapshot>(snapshot).map_err(invalid)?.world;
+
+    for migrate in &MIGRATIONS[version as usize - 1..] {
+        migrate(&mut world);
+    }
+    world.config.validate()?;
+    validate(&world)?;
+    world.reindex();
+
+    Ok(world)
+}
+
+// Rejects worlds that reindex or the simulation would panic on.
+fn validate(world: &World) -> io::Result<()> {
+    let invalid = |message: String| Err(io::Error::new(ErrorKind::InvalidData, message));
+    let size = (world.width(), world.height());
+    if size.0 == 0 || size.1 == 0 {
+        return invalid("world must have at least one tile".to_string());
+    }
+
+    world.cells.validate()?;
+
+    let mut taken = HashSet::new();
+    for cell in world.cells.as_slice() {
+        let (x, y) = cell.position;
+        if x >= size.0 || y >= size.1 {
+            return invalid(format!("cell at {:?} is outside the {}x{} world", cell.position, size.0, size.1));
+        }
+        if !taken.insert(cell.position) {
+            return invalid(format!("more than one cell at {:?}", cell.position));
+        }
+    }
+
+    Ok(())
+}
+
+pub fn save(world: &World, path: impl AsRef<Path>) -> io::Result<()> {
+    fs::write(path, to_string(world)?)
+}
+
+pub fn load(path: impl AsRef<Path>) -> io::Result<World> {
+    from_str(&fs::read_to_string(path)?)
+}
+
+impl World {
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        save(self, path)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<World> {
+        load(path)
+    }
+}
@@ -0,0 +1,251 @@
+use std::ops::Index;
+
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::{grid::Grid, store::CellId};
+
+pub const CHUNK_SIZE: usize = 32;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Layout {
+    #[default]
+    Dense,
+    // Only chunks with at least one cell are allocated.
+    Chunked,
+}
+
+#[derive(Clone, Debug)]
+struct Chunk {
+    tiles: Box<[Option<CellId>]>,
+    occupied: usize,
+}
+
+#[derive(Clone, Debug)]
+enum Tiles {
+    Dense(Grid<Option<CellId>>),
+    Chunked(Grid<Option<Box<Chunk>>>),
+}
+
+// Which cell stands on every tile. Snapshots only keep the size, the tiles are
+// rebuilt from the cells on load.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(from = "SpaceRecord")]
+pub struct Space {
+    width: usize,
+    height: usize,
+    tiles: Tiles,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SpaceRecord {
+    width: usize,
+    height: usize,
+}
+
+impl Space {
+    pub fn new(width: usize, height: usize, layout: Layout) -> Self {
+        let tiles = match layout {
+            Layout::Dense => Tiles::Dense(Grid::new(width, height, None)),
+            Layout::Chunked => Tiles::Chunked(Grid::new(
+                width.div_ceil(CHUNK_SIZE),
+                height.div_ceil(CHUNK_SIZE),
+                None,
+            )),
+        };
+
+        Self { width, height, tiles }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn layout(&self) -> Layout {
+        match self.tiles {
+            Tiles::Dense(_) => Layout::Dense,
+            Tiles::Chunked(_) => Layout::Chunked,
+        }
+    }
+
+    pub fn contains(&self, (x, y): (usize, usize)) -> bool {
+        x < self.width && y < self.height
+    }
+
+    pub fn get(&self, position: (usize, usize)) -> Option<&Option<CellId>> {
+        if !self.contains(position) {
+            return None;
+        }
+
+        match &self.tiles {
+            Tiles::Dense(grid) => grid.get(position),
+            Tiles::Chunked(chunks) => match &chunks[chunk_of(position)] {
+                Some(chunk) => Some(&chunk.tiles[tile_in_chunk(position)]),
+                None => Some(&None),
+            },
+        }
+    }
+
+    pub fn set(&mut self, position: (usize, usize), id: Option<CellId>) {
+        assert!(self.contains(position), "tile {:?} is out of the space", position);
+
+        match &mut self.tiles {
+            Tiles::Dense(grid) => grid[position] = id,
+            Tiles::Chunked(chunks) => {
+                let slot = &mut chunks[chunk_of(position)];
+                if slot.is_none() && id.is_none() {
+                    return;
+                }
+
+                let chunk = slot.get_or_insert_with(|| Box::new(Chunk {
+                    tiles: vec![None; CHUNK_SIZE * CHUNK_SIZE].into_boxed_slice(),
+                    occupied: 0,
+                }));
+                let tile = &mut chunk.tiles[tile_in_chunk(position)];
+                match (tile.is_some(), id.is_some()) {
+                    (false, true) => chunk.occupied += 1,
+                    (true, false) => chunk.occupied -= 1,
+                    _ => {}
+                }
+                *tile = id;
+
+                if chunk.occupied == 0 {
+                    *slot = None;
+                }
+            }
+        }
+    }
+
+    // Occupied tiles in [min, max). With the chunked layout only the
+    // allocated chunks overlapping the area are visited.
+    pub fn occupied(&self, min: (usize, usize), max: (usize, usize)) -> Vec<((usize, usize), CellId)> {
+        let max = (max.0.min(self.width), max.1.min(self.height));
+        let mut occupied = vec![];
+        if min.0 >= max.0 || min.1 >= max.1 {
+            return occupied;
+        }
+
+        match &self.tiles {
+            Tiles::Dense(grid) => {
+                for y in min.1..max.1 {
+                    for x in min.0..max.0 {
+                        if let Some(id) = grid[(x, y)] {
+                            occupied.push(((x, y), id));
+                        }
+                    }
+                }
+            }
+            Tiles::Chunked(chunks) => {
+                let (from, to) = (chunk_of(min), chunk_of((max.0 - 1, max.1 - 1)));
+                for cy in from.1..=to.1 {
+                    for cx in from.0..=to.0 {
+                        let Some(chunk) = &chunks[(cx, cy)] else {
+                            continue;
+                        };
+
+                        for (i, tile) in chunk.tiles.iter().enumerate() {
+                            let position = (cx * CHUNK_SIZE + i % CHUNK_SIZE, cy * CHUNK_SIZE + i / CHUNK_SIZE);
+                            let inside = position.0 >= min.0 && position.0 < max.0
+                                && position.1 >= min.1 && position.1 < max.1;
+                            if let (Some(id), true) = (tile, inside) {
+                                occupied.push((position, *id));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        occupied
+    }
+}
+
+fn chunk_of((x, y): (usize, usize)) -> (usize, usize) {
+    (x / CHUNK_SIZE, y / CHUNK_SIZE)
+}
+
+fn tile_in_chunk((x, y): (usize, usize)) -> usize {
+    (y % CHUNK_SIZE) * CHUNK_SIZE + x % CHUNK_SIZE
+}
+
+impl Index<(usize, usize)> for Space {
+    type Output = Option<CellId>;
+
+    fn index(&self, position: (usize, usize)) -> &Option<CellId> {
+        self.get(position)
+            .unwrap_or_else(|| panic!("tile {:?} is out of the space", position))
+    }
+}
+
+impl Serialize for Space {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SpaceRecord { width: self.width, height: self.height }.serialize(serializer)
+    }
+}
+
+impl From<SpaceRecord> for Space {
+    fn from(record: SpaceRecord) -> Self {
+        Self::new(record.width, record.height, Layout::Dense)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(index: u32) -> CellId {
+        CellId { index, generation: 0 }
+    }
+
+    fn chunks(space: &Space) -> usize {
+        match &space.tiles {
+            Tiles::Dense(_) => 0,
+            Tiles::Chunked(chunks) => chunks.iter().filter(|(_, chunk)| chunk.is_some()).count(),
+        }
+    }
+
+    #[test]
+    fn empty_chunks_are_freed() {
+        let mut space = Space::new(CHUNK_SIZE * 3, CHUNK_SIZE * 3, Layout::Chunked);
+        assert_eq!(chunks(&space), 0);
+
+        let (a, b) = ((CHUNK_SIZE, CHUNK_SIZE), (CHUNK_SIZE + 1, CHUNK_SIZE * 2 - 1));
+        space.set(a, Some(id(0)));
+        space.set(b, Some(id(1)));
+        space.set(b, Some(id(2)));
+        assert_eq!(chunks(&space), 1);
+
+        space.set(a, None);
+        assert_eq!(chunks(&space), 1);
+        space.set(b, None);
+        assert_eq!(chunks(&space), 0);
+        assert_eq!(space[b], None);
+    }
+
+    #[test]
+    fn occupied_stays_in_the_area() {
+        for layout in [Layout::Dense, Layout::Chunked] {
+            let mut space = Space::new(CHUNK_SIZE * 2, CHUNK_SIZE * 2, layout);
+            let tiles = [(0, 0), (5, 5), (6, 5), (5, 6), (CHUNK_SIZE, 5), (CHUNK_SIZE * 2 - 1, CHUNK_SIZE * 2 - 1)];
+            for (i, &position) in tiles.iter().enumerate() {
+                space.set(position, Some(id(i as u32)));
+            }
+
+            let occupied = |min, max| {
+                let mut occupied: Vec<(usize, usize)> = space.occupied(min, max)
+                    .into_iter()
+                    .map(|(position, _)| position)
+                    .collect();
+                occupied.sort_unstable();
+                occupied
+            };
+            assert_eq!(occupied((5, 5), (6, 6)), [(5, 5)], "{:?}", layout);
+            assert_eq!(occupied((5, 5), (CHUNK_SIZE + 1, 6)), [(5, 5), (6, 5), (CHUNK_SIZE, 5)], "{:?}", layout);
+            assert_eq!(occupied((1, 1), (5, 5)), [], "{:?}", layout);
+            assert_eq!(occupied((0, 0), (usize::MAX, usize::MAX)).len(), tiles.len(), "{:?}", layout);
+        }
+    }
+}
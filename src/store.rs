@@ -0,0 +1,185 @@
+use std::{
+    io::{self, ErrorKind},
+    ops::{Index, IndexMut},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::cell::Cell;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct CellId {
+    pub index: u32,
+    pub generation: u32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Slot {
+    generation: u32,
+    dense: Option<u32>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CellStore {
+    slots: Vec<Slot>,
+    free: Vec<u32>,
+    ids: Vec<CellId>,
+    cells: Vec<Cell>,
+}
+
+impl CellStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn insert(&mut self, cell: Cell) -> CellId {
+        let dense = self.cells.len() as u32;
+        let id = match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                slot.dense = Some(dense);
+                CellId { index, generation: slot.generation }
+            }
+            None => {
+                self.slots.push(Slot { generation: 0, dense: Some(dense) });
+                CellId { index: self.slots.len() as u32 - 1, generation: 0 }
+            }
+        };
+
+        self.ids.push(id);
+        self.cells.push(cell);
+
+        id
+    }
+
+    pub fn remove(&mut self, id: CellId) -> Option<Cell> {
+        let dense = self.dense(id)?;
+
+        let slot = &mut self.slots[id.index as usize];
+        slot.dense = None;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(id.index);
+
+        self.ids.swap_remove(dense);
+        let cell = self.cells.swap_remove(dense);
+        if let Some(moved) = self.ids.get(dense) {
+            self.slots[moved.index as usize].dense = Some(dense as u32);
+        }
+
+        Some(cell)
+    }
+
+    pub fn contains(&self, id: CellId) -> bool {
+        self.dense(id).is_some()
+    }
+
+    pub fn get(&self, id: CellId) -> Option<&Cell> {
+        self.dense(id).map(|dense| &self.cells[dense])
+    }
+
+    pub fn get_mut(&mut self, id: CellId) -> Option<&mut Cell> {
+        self.dense(id).map(|dense| &mut self.cells[dense])
+    }
+
+    pub fn ids(&self) -> &[CellId] {
+        &self.ids
+    }
+
+    pub fn as_slice(&self) -> &[Cell] {
+        &self.cells
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [Cell] {
+        &mut self.cells
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (CellId, &Cell)> {
+        self.ids.iter().copied().zip(self.cells.iter())
+    }
+
+    // Checks that slots, ids and cells point at each other, which a store
+    // read from a snapshot does not have to do.
+    pub(crate) fn validate(&self) -> io::Result<()> {
+        let linked = self.ids.len() == self.cells.len()
+            && self.ids.iter().enumerate().all(|(dense, &id)| self.dense(id) == Some(dense))
+            && self.slots.iter().filter(|slot| slot.dense.is_some()).count() == self.ids.len();
+
+        let mut free = self.free.clone();
+        free.sort_unstable();
+        free.dedup();
+        let reusable = free.len() == self.free.len()
+            && free.iter().all(|&index| self.slots.get(index as usize).is_some_and(|slot| slot.dense.is_none()));
+
+        if linked && reusable {
+            Ok(())
+        } else {
+            Err(io::Error::new(ErrorKind::InvalidData, "cell ids do not match the cells"))
+        }
+    }
+
+    fn dense(&self, id: CellId) -> Option<usize> {
+        let slot = self.slots.get(id.index as usize)?;
+        if slot.generation == id.generation {
+            slot.dense.map(|dense| dense as usize)
+        } else {
+            None
+        }
+    }
+}
+
+impl Index<CellId> for CellStore {
+    type Output = Cell;
+
+    fn index(&self, id: CellId) -> &Cell {
+        self.get(id).expect("cell is no longer alive")
+    }
+}
+
+impl IndexMut<CellId> for CellStore {
+    fn index_mut(&mut self, id: CellId) -> &mut Cell {
+        self.get_mut(id).expect("cell is no longer alive")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SimConfig;
+
+    fn cell(x: usize) -> Cell {
+        Cell::new((x, 0), &SimConfig::new())
+    }
+
+    #[test]
+    fn ids_outlive_removals() {
+        let mut store = CellStore::new();
+        let first = store.insert(cell(0));
+        let middle = store.insert(cell(1));
+        let last = store.insert(cell(2));
+
+        // The last cell takes the place of the removed one.
+        assert_eq!(store.remove(middle).map(|cell| cell.position), Some((1, 0)));
+        assert_eq!(store.ids(), &[first, last]);
+        assert_eq!(store.get(last).map(|cell| cell.position), Some((2, 0)));
+
+        // The slot is reused, but the old id does not reach the new cell.
+        let new = store.insert(cell(3));
+        assert_eq!(new.index, middle.index);
+        assert!(!store.contains(middle));
+        assert!(store.get(middle).is_none());
+        assert!(store.remove(middle).is_none());
+
+        assert_eq!(store.len(), 3);
+        for (id, x) in [(first, 0), (last, 2), (new, 3)] {
+            assert_eq!(store[id].position, (x, 0));
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+
+use crate::limit;
+
+const VON_NEUMANN: [(i64, i64); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+const MOORE: [(i64, i64); 8] = [(1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1), (0, -1), (1, -1)];
+const HEX_EVEN_ROW: [(i64, i64); 6] = [(1, 0), (0, 1), (-1, 1), (-1, 0), (-1, -1), (0, -1)];
+const HEX_ODD_ROW: [(i64, i64); 6] = [(1, 0), (1, 1), (0, 1), (-1, 0), (0, -1), (1, -1)];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Topology {
+    #[default]
+    VonNeumann,
+    Moore,
+    Hex,
+}
+
+impl Topology {
+    pub fn directions(&self) -> i8 {
+        match self {
+            Topology::VonNeumann => VON_NEUMANN.len() as i8,
+            Topology::Moore => MOORE.len() as i8,
+            Topology::Hex => HEX_EVEN_ROW.len() as i8,
+        }
+    }
+
+    pub fn rotate(&self, direction: i8, by: i8) -> i8 {
+        (direction as i16 + by as i16).rem_euclid(self.directions() as i16) as i8
+    }
+
+    // Hex grids use "odd-r" offset coordinates: odd rows are shifted half a
+    // tile to the right, so the neighbours depend on the parity of the row.
+    pub fn offset(&self, position: (usize, usize), direction: i8) -> (i64, i64) {
+        let direction = direction.rem_euclid(self.directions()) as usize;
+
+        match self {
+            Topology::VonNeumann => VON_NEUMANN[direction],
+            Topology::Moore => MOORE[direction],
+            Topology::Hex if position.1 % 2 == 1 => HEX_ODD_ROW[direction],
+            Topology::Hex => HEX_EVEN_ROW[direction],
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Boundary {
+    #[default]
+    Clamped,
+    Walls,
+    Torus,
+}
+
+impl Boundary {
+    pub fn apply(&self, (x, y): (i64, i64), (width, height): (usize, usize)) -> Option<(usize, usize)> {
+        let (width, height) = (width as i64, height as i64);
+
+        match self {
+            Boundary::Clamped => Some((
+                limit(0, width - 1, x) as usize,
+                limit(0, height - 1, y) as usize,
+            )),
+            Boundary::Walls => {
+                if x < 0 || y < 0 || x >= width || y >= height {
+                    None
+                } else {
+                    Some((x as usize, y as usize))
+                }
+            }
+            Boundary::Torus => Some((
+                x.rem_euclid(width) as usize,
+                y.rem_euclid(height) as usize,
+            )),
+        }
+    }
+}
@@ -0,0 +1,316 @@
+use std::collections::HashSet;
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cell::{Cell, Gen},
+    store::CellId,
+    vm::{self, Registers},
+    world::World,
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UpdateMode {
+    #[default]
+    Sequential,
+    Synchronous,
+    // Same results as Synchronous, computed on all cores.
+    Parallel,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Conflict {
+    #[default]
+    Heaviest,
+    Cancel,
+}
+
+enum Intent {
+    Idle,
+    Reproduce(Claim),
+    Move(Claim),
+    Attack((usize, usize), CellId),
+    Eat((usize, usize), CellId),
+}
+
+// A cell that wants to put something on an empty tile.
+struct Claim {
+    target: (usize, usize),
+    cell: CellId,
+    position: (usize, usize),
+    mass: f32,
+}
+
+fn row_major((x, y): (usize, usize)) -> (usize, usize) {
+    (y, x)
+}
+
+// The cells that get their tile, in row-major order of the tiles.
+fn winners(mut claims: Vec<Claim>, conflict: Conflict) -> Vec<CellId> {
+    claims.sort_by(|a, b| {
+        row_major(a.target).cmp(&row_major(b.target))
+            .then(b.mass.total_cmp(&a.mass))
+            .then(row_major(a.position).cmp(&row_major(b.position)))
+    });
+
+    let mut winners = vec![];
+    for (i, claim) in claims.iter().enumerate() {
+        let first = i == 0 || claims[i - 1].target != claim.target;
+        let contested = !first || claims.get(i + 1).is_some_and(|next| next.target == claim.target);
+
+        let wins = match conflict {
+            Conflict::Heaviest => first,
+            Conflict::Cancel => !contested,
+        };
+        if wins {
+            winners.push(claim.cell);
+        }
+    }
+
+    winners
+}
+
+// The cell's own direction and registers are the only state it changes.
+fn intent(world: &World, id: CellId, cell: &Cell, direction: &mut i8, registers: Registers) -> Intent {
+    let (grid, config) = (&world.grid, &world.config);
+
+    match vm::execute(world, cell, *direction, registers) {
+        Some(Gen::SetDirection(d)) => {
+            *direction = config.topology.rotate(*direction, d);
+            Intent::Idle
+        }
+        Some(gen @ (Gen::Reproduce | Gen::Move)) => {
+            let size = (grid.width(), grid.height());
+            let (position, mass) = (cell.position, cell.mass);
+            let claim = match config.neighbor(size, position, *direction) {
+                Some(target) if grid[target].is_none() => Claim { target, cell: id, position, mass },
+                _ => return Intent::Idle,
+            };
+
+            match gen {
+                Gen::Move => Intent::Move(claim),
+                _ if mass > cell.min_mass_division => Intent::Reproduce(claim),
+                _ => Intent::Idle,
+            }
+        }
+        Some(Gen::Attack) => Intent::Attack(cell.position, id),
+        Some(Gen::Eat) => Intent::Eat(cell.position, id),
+        _ => Intent::Idle,
+    }
+}
+
+impl World {
+    // Every cell decides what to do from the state at the start of the tick,
+    // then the intents are applied in an order that only depends on positions:
+    // births first (one per free tile, chosen by the conflict policy), then
+    // moves the same way, then attacks and eating, then feeding and deaths.
+    // Only the per-cell parts run in parallel, so both ways give the same
+    // world.
+    pub(crate) fn step_synchronous(&mut self, parallel: bool) {
+        let ids = self.cells.ids().to_vec();
+
+        // The state cells change is copied out of them for the duration, so
+        // that the rest of the world can be shared between threads.
+        let mut states: Vec<(usize, f32, i8)> = self.cells.as_slice()
+            .iter()
+            .map(|cell| (cell.step, cell.register, cell.direction))
+            .collect();
+        let world = &*self;
+        let decide = |((&id, cell), (step, register, direction)): ((&CellId, &Cell), &mut (usize, f32, i8))| {
+            intent(world, id, cell, direction, Registers { step, register })
+        };
+
+        let cells = self.cells.as_slice();
+        let intents: Vec<Intent> = if parallel {
+            ids.par_iter()
+                .zip(cells.par_iter())
+                .zip(states.par_iter_mut())
+                .map(decide)
+                .collect()
+        } else {
+            ids.iter()
+                .zip(cells.iter())
+                .zip(states.iter_mut())
+                .map(decide)
+                .collect()
+        };
+
+        for (cell, (step, register, direction)) in self.cells.as_mut_slice().iter_mut().zip(states) {
+            (cell.step, cell.register, cell.direction) = (step, register, direction);
+        }
+
+        let mut births = vec![];
+        let mut moves = vec![];
+        let mut attackers = vec![];
+        let mut eaters = vec![];
+        for intent in intents {
+            match intent {
+                Intent::Idle => {}
+                Intent::Reproduce(claim) => births.push(claim),
+                Intent::Move(claim) => moves.push(claim),
+                Intent::Attack(position, id) => attackers.push((position, id)),
+                Intent::Eat(position, id) => eaters.push((position, id)),
+            }
+        }
+
+        for id in winners(births, self.config.conflict) {
+            self.reproduce(id);
+        }
+        // A tile taken by a newborn stays taken, the move just fails.
+        for id in winners(moves, self.config.conflict) {
+            self.relocate(id);
+        }
+
+        // Killed cells stay in place until the deaths, but they neither
+        // attack nor can be attacked, nor eat, feed or shade anymore.
+        let mut killed = HashSet::new();
+        attackers.sort_by_key(|(position, _)| row_major(*position));
+        for (_, id) in attackers {
+            if killed.contains(&id) {
+                continue;
+            }
+            if let Some(victim) = self.prey(id).filter(|victim| !killed.contains(victim)) {
+                if self.attack(id, victim) {
+                    self.shade.remove(self.cells[victim].position);
+                    killed.insert(victim);
+                }
+            }
+        }
+
+        eaters.sort_by_key(|(position, _)| row_major(*position));
+        for (_, id) in eaters {
+            if !killed.contains(&id) {
+                self.eat(id);
+            }
+        }
+
+        // Newborns were appended after the living cells and are not fed yet.
+        let living = ids.len();
+        if parallel {
+            let mut food: Vec<f32> = self.cells.as_slice()[..living]
+                .par_iter()
+                .map(|cell| self.food(cell.position))
+                .collect();
+            for (&id, food) in ids.iter().zip(food.iter_mut()) {
+                if killed.contains(&id) {
+                    continue;
+                }
+
+                let position = self.cells[id].position;
+                self.deplete(position, *food);
+                *food += self.absorb(position);
+                *food += self.light(position);
+            }
+
+            let (config, killed) = (&self.config, &killed);
+            self.cells.as_mut_slice()[..living]
+                .par_iter_mut()
+                .zip(ids.par_iter())
+                .zip(food)
+                .filter(|((_, id), _)| !killed.contains(id))
+                .for_each(|((cell, _), food)| cell.feed(food, config));
+        } else {
+            for &id in ids.iter().filter(|id| !killed.contains(id)) {
+                self.feed(id);
+            }
+        }
+
+        // Nothing has been removed yet, so the living cells are still the
+        // first ones and in the order of ids, followed by the newborns.
+        let dying: Vec<CellId> = self.cells.iter()
+            .enumerate()
+            .filter(|&(i, (id, cell))| {
+                killed.contains(&id)
+                    || (i < living && (cell.time_life > cell.max_time_life || cell.mass < cell.min_mass))
+            })
+            .map(|(_, (id, _))| id)
+            .collect();
+        for id in dying {
+            self.kill(id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::SimConfig, snapshot};
+
+    // Frequent mutations, so that species meet and fight.
+    fn run(update: UpdateMode, seed: u64, ticks: usize) -> World {
+        let config = SimConfig {
+            update,
+            mutation_probability: 0.2,
+            finite_nutrients: true,
+            light: true,
+            ..SimConfig::new()
+        };
+        let mut world = World::with_config(24, 24, seed, config);
+        world.run(ticks);
+
+        world
+    }
+
+    // Snapshots hold everything that decides how a world goes on.
+    fn state(world: &World) -> String {
+        snapshot::to_string(world).unwrap()
+    }
+
+    #[test]
+    fn parallel_matches_synchronous() {
+        let synchronous = run(UpdateMode::Synchronous, 7, 150);
+        let mut parallel = run(UpdateMode::Parallel, 7, 150);
+        assert!(synchronous.cells.len() > 50);
+
+        parallel.config.update = UpdateMode::Synchronous;
+        assert_eq!(state(&synchronous), state(&parallel));
+    }
+
+    #[test]
+    fn sequential_repeats_for_a_seed() {
+        let first = run(UpdateMode::Sequential, 7, 150);
+        assert!(first.cells.len() > 50);
+        assert_eq!(state(&first), state(&run(UpdateMode::Sequential, 7, 150)));
+        assert_ne!(state(&first), state(&run(UpdateMode::Sequential, 8, 150)));
+    }
+
+    #[test]
+    fn loaded_world_goes_on_the_same() {
+        for update in [UpdateMode::Sequential, UpdateMode::Synchronous] {
+            let mut world = run(update, 7, 100);
+            let mut loaded = snapshot::from_str(&state(&world)).unwrap();
+
+            world.run(100);
+            loaded.run(100);
+            assert_eq!(state(&world), state(&loaded), "{:?}", update);
+        }
+    }
+
+    #[test]
+    fn kills_leave_no_detritus() {
+        for update in [UpdateMode::Sequential, UpdateMode::Synchronous, UpdateMode::Parallel] {
+            let config = SimConfig { update, detritus_decay: 0.0, ..SimConfig::new() };
+            let mut world = World::with_config(4, 4, 1, config);
+
+            let attacker = world.cells.ids()[0];
+            let cell = &world.cells[attacker];
+            let (direction, target) = (0..world.config.topology.directions())
+                .find_map(|direction| Some((direction, world.neighbor(cell.position, direction)?)))
+                .unwrap();
+            let mut victim = Cell::new(target, &world.config);
+            victim.species = cell.species + 1;
+            (victim.mass, victim.resistance) = (victim.min_mass + 1.0, 0.0);
+            victim.genome = vec![Gen::SenseMass];
+            let victim = world.spawn(victim).unwrap();
+
+            let cell = &mut world.cells[attacker];
+            (cell.direction, cell.damage, cell.step) = (direction, 10.0, 0);
+            cell.genome = vec![Gen::Attack];
+
+            world.step();
+            assert!(!world.cells.contains(victim), "{:?}", update);
+            assert!(world.detritus.is_empty(), "{:?}", update);
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use crate::{
+    cell::{Cell, Gen},
+    store::CellId,
+    world::World,
+};
+
+// What the genome of a cell changes while it runs. Kept out of the cell so
+// that every cell can run at once in the synchronous modes.
+pub(crate) struct Registers<'a> {
+    pub step: &'a mut usize,
+    pub register: &'a mut f32,
+}
+
+impl World {
+    fn ahead(&self, cell: &Cell, direction: i8) -> Option<Option<CellId>> {
+        self.neighbor(cell.position, direction).map(|target| self.grid[target])
+    }
+
+    fn empty_ahead(&self, cell: &Cell, direction: i8) -> bool {
+        self.ahead(cell, direction) == Some(None)
+    }
+
+    // Some(true) for a cell of the same species, Some(false) for another one.
+    fn kin_ahead(&self, cell: &Cell, direction: i8) -> Option<bool> {
+        let id = self.ahead(cell, direction)??;
+        self.cells.get(id).map(|other| other.species == cell.species)
+    }
+}
+
+// Follows jumps and sensors from the current step until a gene that takes the
+// turn. A genome can run at most as many of them as it has genes in one tick,
+// after that the cell does nothing, like one with an empty genome.
+pub(crate) fn execute(world: &World, cell: &Cell, direction: i8, registers: Registers) -> Option<Gen> {
+    let Registers { step, register } = registers;
+    let genome = &cell.genome;
+    if genome.is_empty() {
+        return None;
+    }
+    let len = genome.len() as i64;
+    if *step >= genome.len() {
+        *step = 0;
+    }
+
+    let flag = |set: bool| if set { 1.0 } else { 0.0 };
+    let sense = |register: &mut f32, value: f32| {
+        *register = value;
+        None
+    };
+    for _ in 0..=genome.len() {
+        let jump = match genome[*step] {
+            Gen::Jump(n) => Some(n),
+            Gen::JumpIfEmptyAhead(n) => world.empty_ahead(cell, direction).then_some(n),
+            Gen::JumpIfEnemyAhead(n) => (world.kin_ahead(cell, direction) == Some(false)).then_some(n),
+            Gen::JumpIfMassAbove(x, n) => (cell.mass > x).then_some(n),
+            Gen::JumpIfRegisterAbove(x, n) => (*register > x).then_some(n),
+            Gen::SenseEmptyAhead => sense(register, flag(world.empty_ahead(cell, direction))),
+            Gen::SenseKinAhead => sense(register, flag(world.kin_ahead(cell, direction) == Some(true))),
+            Gen::SenseForeignAhead => sense(register, flag(world.kin_ahead(cell, direction) == Some(false))),
+            Gen::SenseNutrient => sense(register, world.food(cell.position)),
+            Gen::SenseMass => sense(register, cell.mass),
+            Gen::SenseAge => sense(register, cell.time_life as f32),
+            gen @ (Gen::SetDirection(_) | Gen::Reproduce | Gen::Attack | Gen::Move | Gen::Eat) => return Some(gen),
+        };
+
+        *step = match jump {
+            Some(n) => (*step as i64 + n as i64).rem_euclid(len) as usize,
+            None => (*step + 1) % genome.len(),
+        };
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{light::Shade, topology::Boundary, update::UpdateMode};
+
+    // A 4x4 world with its only cell in the top left corner, facing the
+    // empty tile to its right, which is returned too.
+    fn world(genome: Vec<Gen>) -> (World, CellId, (usize, usize)) {
+        let mut world = World::with_size_and_seed(4, 4, 1);
+        let id = world.cells.ids()[0];
+        world.cells[id].genome = genome;
+
+        let cell = &world.cells[id];
+        let ahead = world.neighbor(cell.position, cell.direction).unwrap();
+        (world, id, ahead)
+    }
+
+    // Puts a cell of the same species or of another one on the tile.
+    fn neighbor(world: &mut World, id: CellId, position: (usize, usize), kin: bool) {
+        let mut other = Cell::new(position, &world.config);
+        other.species = world.cells[id].species + if kin { 0 } else { 1 };
+        world.spawn(other).unwrap();
+    }
+
+    // Runs the genome from the given step, returns the gene that takes the
+    // turn and where the step and the register end up.
+    fn run(world: &World, id: CellId, step: usize) -> (Option<Gen>, usize, f32) {
+        let cell = &world.cells[id];
+        let (mut step, mut register) = (step, cell.register);
+        let gen = execute(world, cell, cell.direction, Registers { step: &mut step, register: &mut register });
+
+        (gen, step, register)
+    }
+
+    // Snapshots can hold cells without genes.
+    #[test]
+    fn empty_genome_does_nothing() {
+        for update in [UpdateMode::Sequential, UpdateMode::Synchronous] {
+            let mut world = World::with_size_and_seed(8, 8, 1);
+            world.config.update = update;
+            world.cells.as_mut_slice()[0].genome.clear();
+
+            world.run(5);
+            assert_eq!(world.cells.len(), 1);
+        }
+    }
+
+    #[test]
+    fn negative_jumps_wrap_around() {
+        let (world, id, _) = world(vec![Gen::Attack, Gen::Jump(-3), Gen::Reproduce, Gen::Jump(-7)]);
+
+        assert!(matches!(run(&world, id, 1), (Some(Gen::Reproduce), 2, _)));
+        assert!(matches!(run(&world, id, 3), (Some(Gen::Attack), 0, _)));
+    }
+
+    #[test]
+    fn endless_loops_idle() {
+        let (mut world, id, _) = world(vec![Gen::Jump(0), Gen::Attack]);
+        assert!(matches!(run(&world, id, 0), (None, 0, _)));
+
+        // Sense, jump back and sense again: len + 1 genes, then the turn ends.
+        world.cells[id].genome = vec![Gen::SenseMass, Gen::Jump(-1)];
+        assert!(matches!(run(&world, id, 0), (None, 1, _)));
+    }
+
+    #[test]
+    fn conditional_jumps() {
+        let (mut world, id, ahead) = world(vec![Gen::JumpIfEmptyAhead(2), Gen::Attack, Gen::Move]);
+        assert!(matches!(run(&world, id, 0), (Some(Gen::Move), 2, _)));
+
+        neighbor(&mut world, id, ahead, true);
+        assert!(matches!(run(&world, id, 0), (Some(Gen::Attack), 1, _)));
+
+        world.cells[id].genome = vec![Gen::JumpIfEnemyAhead(2), Gen::Attack, Gen::Move];
+        assert!(matches!(run(&world, id, 0), (Some(Gen::Attack), 1, _)));
+        let kin = world.cell_at(ahead).unwrap().0;
+        world.cells[kin].species += 1;
+        assert!(matches!(run(&world, id, 0), (Some(Gen::Move), 2, _)));
+
+        world.cells[id].mass = 10.0;
+        world.cells[id].genome = vec![Gen::JumpIfMassAbove(9.0, 2), Gen::Attack, Gen::Move];
+        assert!(matches!(run(&world, id, 0), (Some(Gen::Move), 2, _)));
+        world.cells[id].genome = vec![Gen::JumpIfMassAbove(10.0, 2), Gen::Attack, Gen::Move];
+        assert!(matches!(run(&world, id, 0), (Some(Gen::Attack), 1, _)));
+
+        world.cells[id].register = 0.5;
+        world.cells[id].genome = vec![Gen::JumpIfRegisterAbove(0.0, 2), Gen::Attack, Gen::Move];
+        assert!(matches!(run(&world, id, 0), (Some(Gen::Move), 2, _)));
+        world.cells[id].genome = vec![Gen::JumpIfRegisterAbove(0.5, 2), Gen::Attack, Gen::Move];
+        assert!(matches!(run(&world, id, 0), (Some(Gen::Attack), 1, _)));
+    }
+
+    #[test]
+    fn feeding_moves_to_the_next_gene() {
+        let (mut world, id, _) = world(vec![Gen::Attack, Gen::Move]);
+        let config = world.config.clone();
+
+        world.cells[id].feed(0.0, &config);
+        assert_eq!(world.cells[id].step, 1);
+        world.cells[id].feed(0.0, &config);
+        assert_eq!(world.cells[id].step, 0);
+    }
+
+    #[test]
+    fn sensors_write_the_register() {
+        let (mut world, id, ahead) = world(vec![]);
+        (world.cells[id].mass, world.cells[id].time_life) = (12.5, 7);
+        let sensed = |world: &mut World, gen: Gen| {
+            world.cells[id].genome = vec![gen, Gen::Attack];
+            let (gen, step, register) = run(world, id, 0);
+            assert!(matches!((gen, step), (Some(Gen::Attack), 1)));
+
+            register
+        };
+
+        assert_eq!(sensed(&mut world, Gen::SenseEmptyAhead), 1.0);
+        assert_eq!(sensed(&mut world, Gen::SenseKinAhead), 0.0);
+        assert_eq!(sensed(&mut world, Gen::SenseMass), 12.5);
+        assert_eq!(sensed(&mut world, Gen::SenseAge), 7.0);
+        let food = world.food(world.cells[id].position);
+        assert_eq!(sensed(&mut world, Gen::SenseNutrient), food);
+
+        neighbor(&mut world, id, ahead, true);
+        assert_eq!(sensed(&mut world, Gen::SenseEmptyAhead), 0.0);
+        assert_eq!(sensed(&mut world, Gen::SenseKinAhead), 1.0);
+        assert_eq!(sensed(&mut world, Gen::SenseForeignAhead), 0.0);
+
+        let kin = world.cell_at(ahead).unwrap().0;
+        world.cells[kin].species += 1;
+        assert_eq!(sensed(&mut world, Gen::SenseKinAhead), 0.0);
+        assert_eq!(sensed(&mut world, Gen::SenseForeignAhead), 1.0);
+    }
+
+    #[test]
+    fn moves_cost_only_when_they_succeed() {
+        let (mut world, id, _) = world(vec![Gen::Move]);
+        (world.config.light, world.config.boundary, world.config.move_cost) = (true, Boundary::Walls, 2.0);
+        world.shade = Shade::new(4, world.cells.as_slice().iter().map(|cell| cell.position));
+        let (start, mass) = (world.cells[id].position, world.cells[id].mass);
+        let target = (start.0, start.1 + 1);
+
+        // Into the wall at the top, then into another cell.
+        world.cells[id].direction = 3;
+        world.relocate(id);
+        world.cells[id].direction = 1;
+        neighbor(&mut world, id, target, true);
+        world.relocate(id);
+        assert_eq!((world.cells[id].position, world.cells[id].mass), (start, mass));
+
+        let other = world.cell_at(target).unwrap().0;
+        world.kill(other);
+        assert_eq!(world.shade.above(start), 0);
+
+        world.relocate(id);
+        assert_eq!((world.cells[id].position, world.cells[id].mass), (target, mass - 2.0));
+        assert_eq!((world.grid[start], world.grid[target]), (None, Some(id)));
+        assert_eq!(world.shade.above(start), 1);
+    }
+}
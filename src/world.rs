@@ -1,19 +1,475 @@
-use crate::cell::Cell;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
 
-pub const SIZE_MAP: (usize, usize) = (50, 50);
+use crate::{
+    cell::{Cell, Gen},
+    config::SimConfig,
+    space::Space,
+    info::Info,
+    layer::Layer,
+    light::Shade,
+    store::{CellId, CellStore},
+    update::UpdateMode,
+    vm::{self, Registers},
+};
 
+pub const DEFAULT_SIZE_MAP: (usize, usize) = (50, 50);
+
+#[derive(Serialize, Deserialize)]
 pub struct World {
-    pub cells: ([[i32; SIZE_MAP.0]; SIZE_MAP.1], Vec<Cell>),
+    pub grid: Space,
+    pub cells: CellStore,
     pub nutrient_medium: f32,
     pub speed: usize,
+    pub tick: usize,
+    pub seed: u64,
+    pub rng: ChaCha8Rng,
+    pub config: SimConfig,
+    pub detritus: Layer,
+    // What cells have eaten from the stock of every tile, so that full tiles
+    // take no memory.
+    pub nutrient_deficit: Layer,
+    // Where the cells stand, for the light model. Built at the start of every
+    // tick and patched as cells are born, die and move during it.
+    #[serde(skip)]
+    pub(crate) shade: Shade,
 }
 
 impl World {
     pub fn new() -> Self {
-        Self {
-            cells: ([[-1; SIZE_MAP.0]; SIZE_MAP.1], vec![Cell::new((0, 0))]),
+        Self::with_size(DEFAULT_SIZE_MAP.0, DEFAULT_SIZE_MAP.1)
+    }
+
+    pub fn with_size(width: usize, height: usize) -> Self {
+        Self::with_size_and_seed(width, height, rand::random())
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
+        Self::with_size_and_seed(DEFAULT_SIZE_MAP.0, DEFAULT_SIZE_MAP.1, seed)
+    }
+
+    pub fn with_size_and_seed(width: usize, height: usize, seed: u64) -> Self {
+        Self::with_config(width, height, seed, SimConfig::new())
+    }
+
+    pub fn with_config(width: usize, height: usize, seed: u64, config: SimConfig) -> Self {
+        assert!(width > 0 && height > 0, "world must have at least one tile");
+
+        let mut world = Self {
+            grid: Space::new(width, height, config.layout),
+            cells: CellStore::new(),
             nutrient_medium: 4.0,
-            speed: 0
+            speed: 1,
+            tick: 0,
+            seed,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            config,
+            detritus: Layer::new(),
+            nutrient_deficit: Layer::new(),
+            shade: Shade::default(),
+        };
+        let start = world.config.nutrients.richest((width, height), seed);
+        world.spawn(Cell::new(start, &world.config));
+
+        world
+    }
+
+    pub fn reset(&mut self, seed: u64) {
+        let mut world = Self::with_config(self.width(), self.height(), seed, self.config.clone());
+        world.nutrient_medium = self.nutrient_medium;
+        world.speed = self.speed;
+
+        *self = world;
+    }
+
+    pub fn width(&self) -> usize {
+        self.grid.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.grid.height()
+    }
+
+    // Rebuilds the spatial index from the cells, with the layout from config.
+    pub fn reindex(&mut self) {
+        let mut grid = Space::new(self.width(), self.height(), self.config.layout);
+        for (id, cell) in self.cells.iter() {
+            grid.set(cell.position, Some(id));
+        }
+
+        self.grid = grid;
+    }
+
+    pub fn cell_at(&self, position: (usize, usize)) -> Option<(CellId, &Cell)> {
+        let id = (*self.grid.get(position)?)?;
+        self.cells.get(id).map(|cell| (id, cell))
+    }
+
+    pub fn spawn(&mut self, cell: Cell) -> Option<CellId> {
+        if self.grid.get(cell.position) != Some(&None) {
+            return None;
+        }
+
+        let position = cell.position;
+        let id = self.cells.insert(cell);
+        self.grid.set(position, Some(id));
+        self.shade.insert(position);
+
+        Some(id)
+    }
+
+    pub fn kill(&mut self, id: CellId) -> Option<Cell> {
+        let cell = self.cells.remove(id)?;
+        if self.grid[cell.position] == Some(id) {
+            self.grid.set(cell.position, None);
+        }
+        self.shade.remove(cell.position);
+        // Whatever mass is left becomes detritus on the tile.
+        self.detritus.add(cell.position, cell.mass);
+
+        Some(cell)
+    }
+
+    pub fn run(&mut self, ticks: usize) -> Info {
+        for _ in 0..ticks {
+            self.step();
+        }
+
+        Info::from_cells(self.cells.as_slice())
+    }
+
+    pub fn step(&mut self) -> Info {
+        self.shade = if self.config.light {
+            Shade::new(self.width(), self.cells.as_slice().iter().map(|cell| cell.position))
+        } else {
+            Shade::default()
+        };
+
+        match self.config.update {
+            UpdateMode::Sequential => self.step_sequential(),
+            UpdateMode::Synchronous => self.step_synchronous(false),
+            UpdateMode::Parallel => self.step_synchronous(true),
+        }
+
+        self.renew_nutrients();
+        self.detritus.decay(self.config.detritus_decay);
+        self.tick += 1;
+
+        Info::from_cells(self.cells.as_slice())
+    }
+
+    fn step_sequential(&mut self) {
+        let ids = self.cells.ids().to_vec();
+
+        for id in ids {
+            if !self.cells.contains(id) {
+                continue;
+            }
+
+            let cell = &self.cells[id];
+            let (mut step, mut register) = (cell.step, cell.register);
+            let gen = vm::execute(self, cell, cell.direction, Registers { step: &mut step, register: &mut register });
+            let cell = &mut self.cells[id];
+            (cell.step, cell.register) = (step, register);
+
+            match gen {
+                Some(Gen::SetDirection(d)) => cell.to_rotate(d, self.config.topology),
+                Some(Gen::Reproduce) => self.reproduce(id),
+                Some(Gen::Attack) => {
+                    if let Some(victim) = self.prey(id) {
+                        if self.attack(id, victim) {
+                            self.kill(victim);
+                        }
+                    }
+                }
+                Some(Gen::Move) => self.relocate(id),
+                Some(Gen::Eat) => self.eat(id),
+                _ => {}
+            }
+
+            self.feed(id);
+
+            let cell = &self.cells[id];
+            if cell.time_life > cell.max_time_life || cell.mass < cell.min_mass {
+                self.kill(id);
+            }
         }
     }
-}
\ No newline at end of file
+
+    pub fn neighbor(&self, position: (usize, usize), direction: i8) -> Option<(usize, usize)> {
+        self.config.neighbor((self.width(), self.height()), position, direction)
+    }
+
+    pub(crate) fn reproduce(&mut self, id: CellId) {
+        let cell = &self.cells[id];
+        let Some(target) = self.neighbor(cell.position, cell.direction) else {
+            return;
+        };
+
+        if self.grid[target].is_some() || cell.mass <= cell.min_mass_division {
+            return;
+        }
+
+        let cell = &mut self.cells[id];
+        let mut new_cell = cell.clone();
+        new_cell.position = target;
+        new_cell.step = 0;
+        new_cell.register = 0.0;
+        (cell.time_life, new_cell.time_life) = (0, 0);
+        (cell.mass, new_cell.mass) = (cell.mass / 2.0, new_cell.mass / 2.0);
+        new_cell.mutate(&mut self.rng, &self.config);
+
+        self.spawn(new_cell);
+    }
+
+    pub(crate) fn relocate(&mut self, id: CellId) {
+        let cell = &self.cells[id];
+        let position = cell.position;
+        let Some(target) = self.neighbor(position, cell.direction) else {
+            return;
+        };
+
+        if self.grid[target].is_some() {
+            return;
+        }
+
+        self.grid.set(position, None);
+        self.grid.set(target, Some(id));
+        self.shade.remove(position);
+        self.shade.insert(target);
+        let cell = &mut self.cells[id];
+        cell.position = target;
+        cell.mass -= self.config.move_cost;
+    }
+
+    // The cell of another species ahead of the given one, if any.
+    pub(crate) fn prey(&self, id: CellId) -> Option<CellId> {
+        let cell = &self.cells[id];
+        let target = self.neighbor(cell.position, cell.direction)?;
+        let (victim_id, victim) = self.cell_at(target)?;
+
+        if victim_id == id || cell.species == victim.species {
+            None
+        } else {
+            Some(victim_id)
+        }
+    }
+
+    // A failed attack costs the attacker the difference. A successful one
+    // passes a share of what the victim lost, no more than it had, to the
+    // attacker, and if the victim drops below its min mass the attacker also
+    // gets what is left of it. Returns true if the victim was killed,
+    // removing it is up to the caller.
+    pub(crate) fn attack(&mut self, id: CellId, victim: CellId) -> bool {
+        let dmg = self.cells[victim].resistance - self.cells[id].damage;
+        if dmg >= 0.0 {
+            self.cells[id].mass -= dmg;
+            return false;
+        }
+
+        let lost = (-dmg).min(self.cells[victim].mass.max(0.0));
+        self.cells[victim].mass -= lost;
+        self.cells[id].mass += lost * self.config.predation_efficiency;
+
+        let victim = &mut self.cells[victim];
+        if victim.mass < victim.min_mass {
+            let rest = victim.mass.max(0.0);
+            victim.mass = 0.0;
+            self.cells[id].mass += rest;
+            return true;
+        }
+
+        false
+    }
+
+    // Eats detritus from the tile ahead.
+    pub(crate) fn eat(&mut self, id: CellId) {
+        let cell = &self.cells[id];
+        let Some(target) = self.neighbor(cell.position, cell.direction) else {
+            return;
+        };
+
+        let eaten = self.detritus.take(target, self.config.detritus_bite);
+        self.cells[id].mass += eaten;
+    }
+
+    // Detritus taken up from the cell's own tile without any gene.
+    pub(crate) fn absorb(&mut self, position: (usize, usize)) -> f32 {
+        let share = self.config.detritus_absorption * self.detritus.get(position);
+        self.detritus.take(position, share)
+    }
+
+    // The nutrient map: what the medium gives on a tile.
+    pub fn nutrient(&self, position: (usize, usize)) -> f32 {
+        let size = (self.width(), self.height());
+        self.nutrient_medium * self.config.nutrients.sample(position, size, self.seed)
+    }
+
+    // The factor of the schedule for the current tick.
+    pub fn supply(&self) -> f32 {
+        self.config.schedule.factor(self.tick, self.seed)
+    }
+
+    pub fn capacity(&self, position: (usize, usize)) -> f32 {
+        self.config.nutrient_capacity * self.nutrient(position)
+    }
+
+    pub fn stock(&self, position: (usize, usize)) -> f32 {
+        (self.capacity(position) - self.nutrient_deficit.get(position)).max(0.0)
+    }
+
+    // What a cell on the tile gets from the medium this tick.
+    pub fn food(&self, position: (usize, usize)) -> f32 {
+        let nutrient = self.nutrient(position);
+        if !self.config.finite_nutrients {
+            return nutrient * self.supply();
+        }
+
+        // The schedule changes how fast the stock regrows, not how much a
+        // cell can eat of it.
+        nutrient.min(self.stock(position))
+    }
+
+    // Light the cell on the tile absorbs, shaded by the cells above it.
+    pub fn light(&self, position: (usize, usize)) -> f32 {
+        if !self.config.light {
+            return 0.0;
+        }
+
+        let intensity = self.config.light_intensity * self.supply();
+        self.shade.absorbed(position, intensity, self.config.light_absorption)
+    }
+
+    pub(crate) fn deplete(&mut self, position: (usize, usize), eaten: f32) {
+        if self.config.finite_nutrients {
+            self.nutrient_deficit.add(position, eaten);
+        }
+    }
+
+    // Depleted tiles first draw nutrients from their neighbours, then every
+    // tile regrows a share of what the map gives per tick. A neighbour asked
+    // for more than its stock gives every tile a part of it, so diffusion
+    // only moves nutrients around.
+    fn renew_nutrients(&mut self) {
+        if self.nutrient_deficit.is_empty() {
+            return;
+        }
+
+        let diffusion = self.config.nutrient_diffusion.clamp(0.0, 1.0);
+        let directions = self.config.topology.directions();
+        let world = &*self;
+        let neighbors = |position| {
+            (0..directions)
+                .filter_map(move |direction| world.neighbor(position, direction))
+                .filter(move |&neighbor| neighbor != position)
+        };
+        let share = |position, amount| diffusion * amount / neighbors(position).count() as f32;
+
+        let mut asked = Layer::new();
+        for (position, amount) in self.nutrient_deficit.iter() {
+            for neighbor in neighbors(position) {
+                asked.add(neighbor, share(position, amount));
+            }
+        }
+
+        let mut deficit = Layer::new();
+        for (position, amount) in self.nutrient_deficit.iter() {
+            let mut drawn = 0.0;
+            for neighbor in neighbors(position) {
+                let flow = share(position, amount) * (world.stock(neighbor) / asked.get(neighbor)).min(1.0);
+                deficit.add(neighbor, flow);
+                drawn += flow;
+            }
+            deficit.add(position, amount - drawn);
+        }
+
+        // The capacity only matters here if it was lowered while running.
+        let regeneration = self.config.nutrient_regeneration.max(0.0) * self.supply();
+        deficit.update(|position, amount| {
+            (amount - regeneration * self.nutrient(position)).min(self.capacity(position))
+        });
+        self.nutrient_deficit = deficit;
+    }
+
+    pub(crate) fn feed(&mut self, id: CellId) {
+        let position = self.cells[id].position;
+        let food = self.food(position);
+        self.deplete(position, food);
+
+        let food = food + self.absorb(position) + self.light(position);
+        self.cells[id].feed(food, &self.config);
+    }
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{nutrient::Nutrients, topology::Topology};
+
+    #[test]
+    fn diffusion_keeps_the_stock() {
+        let mut world = World::with_size_and_seed(6, 6, 1);
+        (world.config.nutrients, world.config.topology) = (Nutrients::Radial, Topology::Moore);
+        (world.config.finite_nutrients, world.config.nutrient_regeneration) = (true, 0.0);
+        world.config.nutrient_diffusion = 1.0;
+
+        // Every tile is drained but one, so most neighbours have nothing to
+        // give.
+        let tiles: Vec<(usize, usize)> = (0..6).flat_map(|y| (0..6).map(move |x| (x, y))).collect();
+        for &position in &tiles {
+            let drained = if position == (2, 3) { 0.5 } else { 1.0 };
+            world.nutrient_deficit.add(position, drained * world.capacity(position));
+        }
+        let total = |world: &World| -> f32 { tiles.iter().map(|&position| world.stock(position)).sum() };
+
+        let before = total(&world);
+        for _ in 0..5 {
+            world.renew_nutrients();
+            assert!((total(&world) - before).abs() < 1e-3, "{} != {}", total(&world), before);
+        }
+    }
+
+    #[test]
+    fn shade_follows_the_cells() {
+        for update in [UpdateMode::Sequential, UpdateMode::Synchronous] {
+            let config = SimConfig { update, light: true, mutation_probability: 0.2, ..SimConfig::new() };
+            let mut world = World::with_config(12, 12, 3, config);
+
+            for _ in 0..100 {
+                world.step();
+                let shade = Shade::new(12, world.cells.as_slice().iter().map(|cell| cell.position));
+                for position in (0..12).flat_map(|y| (0..12).map(move |x| (x, y))) {
+                    assert_eq!(world.shade.above(position), shade.above(position), "{:?}", update);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn attack_takes_no_more_than_the_victim_has() {
+        let mut world = World::with_size_and_seed(4, 4, 1);
+        world.config.predation_efficiency = 1.0;
+
+        let attacker = world.cells.ids()[0];
+        let position = world.cells[attacker].position;
+        let mut victim = Cell::new(((position.0 + 1) % 4, position.1), &world.config);
+        victim.species = world.cells[attacker].species + 1;
+        (victim.mass, victim.min_mass, victim.resistance) = (2.0, 0.0, 0.0);
+        world.cells[attacker].damage = 10.0;
+        let victim = world.spawn(victim).unwrap();
+
+        let before: f32 = world.cells.as_slice().iter().map(|cell| cell.mass).sum();
+        world.attack(attacker, victim);
+        let after: f32 = world.cells.as_slice().iter().map(|cell| cell.mass).sum();
+
+        assert_eq!(world.cells[victim].mass, 0.0);
+        assert_eq!(before, after);
+    }
+}
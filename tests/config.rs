@@ -0,0 +1,54 @@
+use cells::{
+    config::SimConfig,
+    topology::{Boundary, Topology},
+};
+
+#[test]
+fn rejects_values_the_simulation_cannot_run_with() {
+    let invalid = [
+        "(mutation_mass: -1.0)",
+        "(mutation_time_life: 18446744073709551615)",
+        "(mutation_direction: -1)",
+        "(max_mass: 0)",
+        "(min_mass: 0)",
+        "(max_time_life: 0)",
+        "(predation_efficiency: 2.0)",
+        "(mutation_probability: 1.5)",
+        "(nutrients: Patches(size: 0.0))",
+    ];
+
+    for config in invalid {
+        assert!(config.parse::<SimConfig>().is_err(), "{}", config);
+    }
+}
+
+#[test]
+fn accepts_the_defaults() {
+    let default = include_str!("../configs/default.ron").parse::<SimConfig>().unwrap();
+    assert_eq!(default, SimConfig::new());
+    assert!("(mutation_direction: 100)".parse::<SimConfig>().is_ok());
+}
+
+#[test]
+fn neighbours_see_each_other() {
+    for topology in [Topology::VonNeumann, Topology::Moore, Topology::Hex] {
+        for boundary in [Boundary::Clamped, Boundary::Walls, Boundary::Torus] {
+            let config = &SimConfig { topology, boundary, ..SimConfig::new() };
+            let neighbors = |size, position| {
+                (0..topology.directions()).filter_map(move |direction| config.neighbor(size, position, direction))
+            };
+
+            for size in [(1, 1), (2, 3), (5, 5), (6, 5), (5, 6), (6, 6)] {
+                let tiles = (0..size.1).flat_map(|y| (0..size.0).map(move |x| (x, y)));
+                for position in tiles {
+                    for neighbor in neighbors(size, position).filter(|&neighbor| neighbor != position) {
+                        assert!(
+                            neighbors(size, neighbor).any(|back| back == position),
+                            "{:?} {:?} {:?}: {:?} -> {:?}", topology, boundary, size, position, neighbor,
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use cells::{snapshot, world::World};
+
+// A 12x12 world with seed 1, saved by cells-cli after 30 ticks.
+const FIXTURE: &str = include_str!("fixtures/snapshot_v1.ron");
+
+#[test]
+fn loads_the_current_version() {
+    let world = snapshot::from_str(FIXTURE).unwrap();
+
+    assert_eq!((world.width(), world.height()), (12, 12));
+    assert_eq!(world.tick, 30);
+    assert_eq!(world.seed, 1);
+    assert_eq!(world.cells.len(), 4);
+    for (id, cell) in world.cells.iter() {
+        assert_eq!(world.grid[cell.position], Some(id));
+    }
+}
+
+#[test]
+fn loaded_worlds_keep_running() {
+    let mut world = snapshot::from_str(FIXTURE).unwrap();
+    world.run(10);
+
+    let saved = snapshot::to_string(&world).unwrap();
+    let loaded = snapshot::from_str(&saved).unwrap();
+    assert_eq!(saved, snapshot::to_string(&loaded).unwrap());
+}
+
+#[test]
+fn rejects_unknown_versions() {
+    let saved = snapshot::to_string(&World::with_size_and_seed(4, 4, 1)).unwrap();
+    let current = format!("(version:{},", snapshot::SNAPSHOT_VERSION);
+    assert!(saved.starts_with(&current));
+
+    for version in [0, snapshot::SNAPSHOT_VERSION + 1] {
+        let other = saved.replacen(&current, &format!("(version:{},", version), 1);
+        assert!(snapshot::from_str(&other).is_err(), "{}", version);
+    }
+    assert!(snapshot::from_str(&saved.replacen(&current, "(", 1)).is_err());
+}
+
+#[test]
+fn rejects_malformed_worlds() {
+    let malformed = [
+        ("a tile outside", FIXTURE.replacen("position:(0,1)", "position:(99,99)", 1)),
+        ("a shared tile", FIXTURE.replacen("position:(1,1)", "position:(0,1)", 1)),
+        ("no tiles", FIXTURE.replacen("grid:(width:12,", "grid:(width:0,", 1)),
+        ("a missing id", FIXTURE.replacen("ids:[(index:1,generation:0),", "ids:[", 1)),
+        ("a stale id", FIXTURE.replacen("ids:[(index:1,generation:0),", "ids:[(index:1,generation:1),", 1)),
+        ("a free slot in use", FIXTURE.replacen("free:[]", "free:[0]", 1)),
+    ];
+
+    for (name, snapshot) in malformed {
+        assert_ne!(snapshot, FIXTURE, "{}", name);
+        let err = snapshot::from_str(&snapshot).err().unwrap_or_else(|| panic!("{} was loaded", name));
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData, "{}", name);
+    }
+}